@@ -1,6 +1,10 @@
 use serde_json::{from_str, to_string};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use serde_catch_all::serde_catch_all;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_catch_all::{serde_catch_all, CatchAllDeserialize, CatchAllSerialize, Content, Lossless};
 
 #[serde_catch_all]
 #[derive(Debug, PartialEq, Eq)]
@@ -14,6 +18,341 @@ enum Example {
     Other(String),
 }
 
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Renamed {
+    FirstOption,
+    #[serde(rename = "second")]
+    SecondOption,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Channel {
+    Stable,
+    #[serde(other)]
+    Unknown(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    High,
+    Low,
+    #[serde(other)]
+    Unrecognized,
+}
+
+#[derive(Debug, PartialEq, Eq, CatchAllSerialize, CatchAllDeserialize)]
+#[catch_all(case_insensitive)]
+#[serde(rename_all = "snake_case")]
+enum Region {
+    UsEast,
+    EuWest,
+    #[catch_all]
+    Other(String),
+}
+
+#[derive(Debug, PartialEq, Eq, CatchAllDeserialize)]
+enum Tier {
+    Free,
+    Paid,
+    #[catch_all]
+    Other(String),
+}
+
+// Only deserializing is derived, so tiers can be written in a form of our own
+impl Serialize for Tier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Tier::Free => serializer.serialize_str("FREE"),
+            Tier::Paid => serializer.serialize_str("PAID"),
+            Tier::Other(other) => serializer.serialize_str(other),
+        }
+    }
+}
+
+#[serde_catch_all(deserialize_only)]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum WebhookEvent {
+    PaymentSucceeded,
+    #[serde(rename = "payment_failed")]
+    PaymentFailed,
+    #[serde(alias = "payment_declined")]
+    PaymentRejected,
+    #[catch_all]
+    Other(Box<str>),
+}
+
+// Written in a form of our own, which `deserialize_only` leaves room for
+impl Serialize for WebhookEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(matches!(self, WebhookEvent::PaymentSucceeded))
+    }
+}
+
+#[serde_catch_all(serialize_only)]
+#[serde(rename_all = "lowercase")]
+enum Notifier {
+    Email,
+    Sms,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Plan {
+    #[serde(rename(serialize = "starter", deserialize = "basic"))]
+    Starter,
+    #[serde(rename(deserialize = "business"))]
+    Business,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Phase {
+    Running,
+    #[serde(skip)]
+    Uninitialized,
+    #[serde(skip_deserializing)]
+    Done,
+    #[serde(skip_serializing)]
+    Finished,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all(normalize(trim, ascii_case, separators))]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Lenient {
+    OnHold,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Generic<S> {
+    Known,
+    #[catch_all]
+    Other(S),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Shared {
+    Known,
+    #[catch_all]
+    Other(Arc<str>),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Borrowed<'a> {
+    Known,
+    #[catch_all]
+    #[serde(borrow)]
+    Other(Cow<'a, str>),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Coded {
+    Unspecified = 0,
+    Active = 1,
+    Inactive = 2,
+    #[catch_all]
+    Unknown(String),
+    #[catch_all]
+    UnknownCode(i64),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq, Eq)]
+enum Level {
+    Low = 1,
+    High = 2,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all(serialize_as = "number_if_binary", non_self_describing)]
+#[derive(Debug, PartialEq, Eq)]
+enum Compact {
+    First = 1,
+    Second = 2,
+    #[catch_all]
+    Unknown(String),
+    #[catch_all]
+    UnknownCode(i32),
+}
+
+#[serde_catch_all(serialize_as = "number_if_binary")]
+#[derive(Debug, PartialEq, Eq)]
+enum Wire {
+    First = 1,
+    Second = 2,
+    #[catch_all]
+    Unknown(String),
+    #[catch_all]
+    UnknownCode(i32),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq)]
+enum Source {
+    Stdin,
+    File(String),
+    Http {
+        url: String,
+    },
+    #[catch_all]
+    Unknown {
+        name: String,
+        value: Option<serde_json::Value>,
+    },
+}
+
+#[serde_catch_all(non_self_describing)]
+#[derive(Debug, PartialEq)]
+enum Sink {
+    #[serde(alias = "Console")]
+    Stdout,
+    File(String),
+    #[serde(rename(deserialize = "Socket"), alias = "Tcp")]
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    #[catch_all]
+    Unknown {
+        name: String,
+        target: Option<String>,
+    },
+}
+
+#[serde_catch_all(tag = "type")]
+#[derive(Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Event {
+    Ping,
+    UserCreated {
+        id: u64,
+        name: String,
+    },
+    #[catch_all]
+    Unknown {
+        kind: String,
+        rest: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+#[serde_catch_all(tag = "type")]
+#[derive(Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Notice {
+    Ping,
+    Alert {
+        level: u8,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[serde_catch_all(tag = "kind", content = "data")]
+#[derive(Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Hook {
+    Ping,
+    Push {
+        repo: String,
+    },
+    Star(u32),
+    #[catch_all]
+    Unknown {
+        kind: String,
+        data: serde_json::Value,
+    },
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq)]
+enum Command {
+    Quit,
+    Echo(String),
+    #[catch_all]
+    Unknown {
+        name: String,
+        args: Content,
+    },
+}
+
+#[serde_catch_all(strict)]
+#[derive(Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Active,
+    Inactive,
+    #[catch_all]
+    Other(String),
+}
+
+static UNKNOWN_SEEN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static UNKNOWN_SIZES: AtomicUsize = AtomicUsize::new(0);
+
+fn record_unknown(enum_name: &'static str, raw: &str) {
+    UNKNOWN_SEEN
+        .lock()
+        .unwrap()
+        .push(format!("{}: {}", enum_name, raw));
+}
+
+fn count_unknown_size(_enum_name: &'static str, _raw: &str) {
+    UNKNOWN_SIZES.fetch_add(1, Ordering::Relaxed);
+}
+
+#[serde_catch_all(observe = count_unknown_size)]
+#[derive(Debug, PartialEq)]
+enum Size {
+    Small,
+    Large,
+    #[catch_all]
+    Other(String),
+}
+
+#[serde_catch_all]
+#[derive(Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct User {
+    user_id: u64,
+    display_name: Option<String>,
+    #[catch_all]
+    extra: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+#[cfg(feature = "serde_json")]
+#[serde_catch_all(tag = "kind", content = "data")]
+#[derive(Debug)]
+enum Routed {
+    Ping,
+    #[catch_all]
+    Unknown {
+        kind: String,
+        data: Box<serde_json::value::RawValue>,
+    },
+}
+
 fn main() {
     // Test known variants
     assert_eq!(
@@ -49,5 +388,618 @@ fn main() {
         r#""custom""#
     );
 
+    // Test rename_all, with a variant-level rename taking precedence
+    assert_eq!(
+        from_str::<Renamed>(r#""first-option""#).unwrap(),
+        Renamed::FirstOption
+    );
+    assert_eq!(
+        from_str::<Renamed>(r#""FirstOption""#).unwrap(),
+        Renamed::Other("FirstOption".into())
+    );
+    assert_eq!(
+        to_string(&Renamed::FirstOption).unwrap(),
+        r#""first-option""#
+    );
+    assert_eq!(to_string(&Renamed::SecondOption).unwrap(), r#""second""#);
+
+    // Test `#[serde(other)]` as the catch-all marker, keeping or dropping the value
+    assert_eq!(
+        from_str::<Channel>(r#""nightly""#).unwrap(),
+        Channel::Unknown("nightly".into())
+    );
+    assert_eq!(
+        to_string(&Channel::Unknown("nightly".into())).unwrap(),
+        r#""nightly""#
+    );
+    assert_eq!(from_str::<Priority>(r#""high""#).unwrap(), Priority::High);
+    assert_eq!(
+        from_str::<Priority>(r#""urgent""#).unwrap(),
+        Priority::Unrecognized
+    );
+    assert_eq!(
+        to_string(&Priority::Unrecognized).unwrap(),
+        r#""unrecognized""#
+    );
+    assert_eq!(
+        "medium".parse::<Priority>().unwrap(),
+        Priority::Unrecognized
+    );
+    assert!(Priority::Unrecognized.is_unknown());
+
+    // Test the derives, which leave the enum as written
+    assert_eq!(from_str::<Region>(r#""US_EAST""#).unwrap(), Region::UsEast);
+    assert_eq!(
+        from_str::<Region>(r#""ap_south""#).unwrap(),
+        Region::Other("ap_south".into())
+    );
+    assert_eq!(to_string(&Region::EuWest).unwrap(), r#""eu_west""#);
+    assert_eq!(Region::UsEast.as_str(), "us_east");
+    assert_eq!(Region::KNOWN_NAMES, &["us_east", "eu_west"]);
+    assert_eq!(from_str::<Tier>(r#""Paid""#).unwrap(), Tier::Paid);
+    assert_eq!(Tier::from("Gold"), Tier::Other("Gold".into()));
+    assert_eq!(to_string(&Tier::Paid).unwrap(), r#""PAID""#);
+
+    // Test an enum that only derives deserializing
+    assert_eq!(
+        from_str::<WebhookEvent>(r#""payment_declined""#).unwrap(),
+        WebhookEvent::PaymentRejected
+    );
+    assert_eq!(
+        from_str::<WebhookEvent>(r#""refund_issued""#).unwrap(),
+        WebhookEvent::Other("refund_issued".into())
+    );
+    assert_eq!(to_string(&WebhookEvent::PaymentFailed).unwrap(), "false");
+    assert_eq!(to_string(&Notifier::Email).unwrap(), r#""email""#);
+    assert_eq!(to_string(&Notifier::Sms).unwrap(), r#""sms""#);
+    assert_eq!(Notifier::KNOWN_NAMES, ["email", "sms"]);
+    assert!(Notifier::Other("push".into()).is_unknown());
+
+    // Test a rename naming each direction separately
+    assert_eq!(from_str::<Plan>(r#""basic""#).unwrap(), Plan::Starter);
+    assert_eq!(
+        from_str::<Plan>(r#""starter""#).unwrap(),
+        Plan::Other("starter".into())
+    );
+    assert_eq!(to_string(&Plan::Starter).unwrap(), r#""starter""#);
+    assert_eq!(from_str::<Plan>(r#""business""#).unwrap(), Plan::Business);
+    assert_eq!(to_string(&Plan::Business).unwrap(), r#""Business""#);
+    assert_eq!(
+        Plan::ALIASES,
+        [("basic", "starter"), ("business", "Business")]
+    );
+
+    // Test skipped variants, whose names fall to the catch-all or fail to serialize
+    assert_eq!(
+        from_str::<Phase>(r#""Uninitialized""#).unwrap(),
+        Phase::Other("Uninitialized".into())
+    );
+    assert_eq!(
+        from_str::<Phase>(r#""Done""#).unwrap(),
+        Phase::Other("Done".into())
+    );
+    assert_eq!(from_str::<Phase>(r#""Finished""#).unwrap(), Phase::Finished);
+    assert_eq!(to_string(&Phase::Done).unwrap(), r#""Done""#);
+    assert_eq!(
+        to_string(&Phase::Finished).unwrap_err().to_string(),
+        "the enum variant Phase::Finished cannot be serialized"
+    );
+    assert!(to_string(&Phase::Uninitialized).is_err());
+    assert_eq!(Phase::KNOWN_NAMES, ["Running", "Done", "Finished"]);
+    assert_eq!(
+        Phase::known_variants().collect::<Vec<_>>(),
+        [Phase::Running, Phase::Done, Phase::Finished]
+    );
+
+    // Test the string conversions sharing serde's names
+    assert_eq!(Example::OptionB.as_str(), "b");
+    assert_eq!(Example::Other("custom".into()).to_string(), "custom");
+    assert_eq!("alt1".parse::<Example>().unwrap(), Example::OptionC);
+    assert_eq!(Example::from("b"), Example::OptionB);
+    assert_eq!(
+        Example::from(String::from("new")),
+        Example::Other("new".into())
+    );
+    assert_eq!(String::from(Renamed::FirstOption), "first-option");
+
+    // Test the introspection helpers
+    assert_eq!(Example::KNOWN_NAMES, &["OptionA", "b", "OptionC"]);
+    assert_eq!(
+        Example::ALIASES,
+        &[("alt1", "OptionC"), ("alt2", "OptionC")]
+    );
+    assert_eq!(
+        Example::known_variants().collect::<Vec<_>>(),
+        vec![Example::OptionA, Example::OptionB, Example::OptionC]
+    );
+    assert!(Example::OptionA.is_known());
+    assert!(Example::Other("custom".into()).is_unknown());
+
+    // Test normalized matching, which keeps unknown values as received
+    assert_eq!(
+        from_str::<Lenient>(r#"" On-Hold ""#).unwrap(),
+        Lenient::OnHold
+    );
+    assert_eq!("ON HOLD".parse::<Lenient>().unwrap(), Lenient::OnHold);
+    assert_eq!(
+        from_str::<Lenient>(r#"" Paused ""#).unwrap(),
+        Lenient::Other(" Paused ".into())
+    );
+    assert_eq!(to_string(&Lenient::OnHold).unwrap(), r#""on_hold""#);
+
+    // Test lossless wrapping, which serializes the spelling that was received
+    let lossless = from_str::<Lossless<Lenient>>(r#""ON-HOLD""#).unwrap();
+    assert_eq!(*lossless.value(), Lenient::OnHold);
+    assert_eq!(lossless.raw(), "ON-HOLD");
+    assert_eq!(to_string(&lossless).unwrap(), r#""ON-HOLD""#);
+    assert_eq!(
+        to_string(&Lossless::new(Lenient::OnHold)).unwrap(),
+        r#""on_hold""#
+    );
+    assert_eq!(*from_str::<Lossless<Level>>("2").unwrap(), Level::High);
+    assert_eq!(
+        to_string(&Lossless::new(Phase::Finished))
+            .unwrap_err()
+            .to_string(),
+        "the enum variant Phase::Finished cannot be serialized"
+    );
+    assert_eq!(
+        from_str::<Lossless<Status>>(r#""actve""#)
+            .unwrap_err()
+            .to_string(),
+        r#"unknown variant "actve", did you mean "active"? at line 1 column 7"#
+    );
+
+    // Test a generic catch-all payload
+    assert_eq!(
+        from_str::<Generic<Box<str>>>(r#""Known""#).unwrap(),
+        Generic::Known
+    );
+    assert_eq!(
+        from_str::<Generic<Box<str>>>(r#""boxed""#).unwrap(),
+        Generic::Other("boxed".into())
+    );
+    assert_eq!(
+        to_string(&Generic::<Box<str>>::Other("boxed".into())).unwrap(),
+        r#""boxed""#
+    );
+
+    // Test a non-String owned catch-all payload
+    assert_eq!(
+        from_str::<Shared>(r#""shared""#).unwrap(),
+        Shared::Other("shared".into())
+    );
+    assert_eq!(
+        to_string(&Shared::Other("shared".into())).unwrap(),
+        r#""shared""#
+    );
+
+    // Test a borrowed catch-all payload, which only allocates for escaped strings
+    assert!(matches!(
+        from_str::<Borrowed>(r#""plain""#).unwrap(),
+        Borrowed::Other(Cow::Borrowed("plain"))
+    ));
+    assert!(matches!(
+        from_str::<Borrowed>(r#""esc\"aped""#).unwrap(),
+        Borrowed::Other(Cow::Owned(_))
+    ));
+    assert_eq!(
+        to_string(&Borrowed::Other("plain".into())).unwrap(),
+        r#""plain""#
+    );
+
+    // Test integer discriminants next to names, with a numeric catch-all
+    assert_eq!(from_str::<Coded>("1").unwrap(), Coded::Active);
+    assert_eq!(from_str::<Coded>(r#""INACTIVE""#).unwrap(), Coded::Inactive);
+    assert_eq!(from_str::<Coded>("7").unwrap(), Coded::UnknownCode(7));
+    assert_eq!(
+        from_str::<Coded>(r#""RETIRED""#).unwrap(),
+        Coded::Unknown("RETIRED".to_string())
+    );
+    assert_eq!(to_string(&Coded::Unspecified).unwrap(), r#""UNSPECIFIED""#);
+    assert_eq!(to_string(&Coded::UnknownCode(7)).unwrap(), "7");
+    assert_eq!(Coded::UnknownCode(7).to_string(), "7");
+
+    // Test names and codes round-tripping through a format that is not self-describing
+    fn bincode_round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap()
+    }
+    for value in [
+        Compact::Second,
+        Compact::Unknown("x".into()),
+        Compact::UnknownCode(9),
+    ] {
+        assert_eq!(bincode_round_trip(&value), value);
+    }
+    assert_eq!(to_string(&Compact::Second).unwrap(), r#""Second""#);
+
+    // Test plain names and codes from other producers in self-describing binary formats
+    fn to_cbor<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+    fn from_cbor<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> T {
+        ciborium::from_reader(bytes).unwrap()
+    }
+    assert_eq!(from_cbor::<Wire>(&to_cbor(&2)), Wire::Second);
+    assert_eq!(from_cbor::<Wire>(&to_cbor(&9)), Wire::UnknownCode(9));
+    assert_eq!(from_cbor::<Wire>(&to_cbor("First")), Wire::First);
+    assert_eq!(
+        from_cbor::<Wire>(&to_cbor("Third")),
+        Wire::Unknown("Third".into())
+    );
+    assert_eq!(to_cbor(&Wire::Second), to_cbor(&2));
+    assert_eq!(to_cbor(&Wire::Unknown("Third".into())), to_cbor("Third"));
+    assert_eq!(to_cbor(&Level::High), to_cbor("High"));
+    assert_eq!(to_cbor(&Coded::Active), to_cbor("ACTIVE"));
+    assert_eq!(from_cbor::<Level>(&to_cbor(&1)), Level::Low);
+    let msgpack = rmp_serde::to_vec(&1).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<Wire>(&msgpack).unwrap(),
+        Wire::First
+    );
+    assert_eq!(rmp_serde::to_vec(&Wire::First).unwrap(), msgpack);
+    let msgpack = rmp_serde::to_vec("Second").unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<Wire>(&msgpack).unwrap(),
+        Wire::Second
+    );
+
+    // Test a tagged enum whose unit catch-all drops the unknown tag and fields
+    assert_eq!(
+        from_str::<Notice>(r#"{"type":"alert","level":2}"#).unwrap(),
+        Notice::Alert { level: 2 }
+    );
+    assert_eq!(
+        from_str::<Notice>(r#"{"type":"digest","items":[1,2]}"#).unwrap(),
+        Notice::Unknown
+    );
+    assert_eq!(
+        to_string(&Notice::Unknown).unwrap(),
+        r#"{"type":"unknown"}"#
+    );
+
+    // Test an internally tagged enum, where unknown events keep their fields
+    assert_eq!(
+        from_str::<Event>(r#"{"type":"ping"}"#).unwrap(),
+        Event::Ping
+    );
+    let created = from_str::<Event>(r#"{"type":"user_created","id":1,"name":"ann"}"#).unwrap();
+    assert_eq!(
+        created,
+        Event::UserCreated {
+            id: 1,
+            name: "ann".to_string()
+        }
+    );
+    assert_eq!(
+        to_string(&created).unwrap(),
+        r#"{"type":"user_created","id":1,"name":"ann"}"#
+    );
+    let unknown = from_str::<Event>(r#"{"type":"team_created","id":9}"#).unwrap();
+    assert_eq!(unknown.as_str(), "team_created");
+    assert_eq!(
+        to_string(&unknown).unwrap(),
+        r#"{"type":"team_created","id":9}"#
+    );
+
+    // Test an externally tagged enum, where unknown variants keep their payload
+    assert_eq!(from_str::<Source>(r#""Stdin""#).unwrap(), Source::Stdin);
+    assert_eq!(
+        from_str::<Source>(r#"{"File":"a.txt"}"#).unwrap(),
+        Source::File("a.txt".to_string())
+    );
+    assert_eq!(
+        to_string(&Source::Http {
+            url: "u".to_string()
+        })
+        .unwrap(),
+        r#"{"Http":{"url":"u"}}"#
+    );
+    let unknown = from_str::<Source>(r#"{"S3":{"bucket":"logs"}}"#).unwrap();
+    assert_eq!(unknown.as_str(), "S3");
+    assert_eq!(to_string(&unknown).unwrap(), r#"{"S3":{"bucket":"logs"}}"#);
+    let unknown = from_str::<Source>(r#"{"Zed":null}"#).unwrap();
+    assert_eq!(to_string(&unknown).unwrap(), r#"{"Zed":null}"#);
+    let unknown = from_str::<Source>(r#""Zed""#).unwrap();
+    assert_eq!(to_string(&unknown).unwrap(), r#""Zed""#);
+    assert_eq!(Source::KNOWN_NAMES, ["Stdin", "File", "Http"]);
+    assert_eq!(
+        Source::known_variants().collect::<Vec<_>>(),
+        [Source::Stdin]
+    );
+
+    // Test externally tagged values from serde's own derive in self-describing binary formats
+    #[derive(Serialize)]
+    enum Upstream {
+        Stdin,
+        File(String),
+        Http { url: String },
+        S3 { bucket: String },
+    }
+    for (upstream, source) in [
+        (Upstream::Stdin, Source::Stdin),
+        (Upstream::File("a".into()), Source::File("a".into())),
+        (
+            Upstream::Http { url: "u".into() },
+            Source::Http { url: "u".into() },
+        ),
+        (
+            Upstream::S3 {
+                bucket: "logs".into(),
+            },
+            Source::Unknown {
+                name: "S3".into(),
+                value: Some(serde_json::json!({ "bucket": "logs" })),
+            },
+        ),
+    ] {
+        let cbor = to_cbor(&upstream);
+        assert_eq!(from_cbor::<Source>(&cbor), source);
+        assert_eq!(to_cbor(&source), cbor);
+        let msgpack = rmp_serde::to_vec_named(&upstream).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Source>(&msgpack).unwrap(), source);
+        assert_eq!(rmp_serde::to_vec_named(&source).unwrap(), msgpack);
+    }
+
+    // Test serde's enum form in formats that are not self-describing
+    for value in [
+        Sink::Stdout,
+        Sink::File("a.txt".into()),
+        Sink::Tcp {
+            host: "h".into(),
+            port: 80,
+        },
+        Sink::Unknown {
+            name: "Pipe".into(),
+            target: Some("p".into()),
+        },
+        Sink::Unknown {
+            name: "Null".into(),
+            target: None,
+        },
+    ] {
+        assert_eq!(bincode_round_trip(&value), value);
+        assert_eq!(from_cbor::<Sink>(&to_cbor(&value)), value);
+    }
+    #[derive(Serialize)]
+    enum UpstreamSink {
+        Console,
+        File(String),
+        Socket { host: String, port: u16 },
+        Pipe(String),
+    }
+    let msgpack = rmp_serde::to_vec(&UpstreamSink::File("a.txt".into())).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<Sink>(&msgpack).unwrap(),
+        Sink::File("a.txt".into())
+    );
+    let msgpack = rmp_serde::to_vec(&UpstreamSink::Console).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<Sink>(&msgpack).unwrap(),
+        Sink::Stdout
+    );
+    let socket = UpstreamSink::Socket {
+        host: "h".into(),
+        port: 80,
+    };
+    assert_eq!(
+        rmp_serde::from_slice::<Sink>(&rmp_serde::to_vec(&socket).unwrap()).unwrap(),
+        Sink::Tcp {
+            host: "h".into(),
+            port: 80
+        }
+    );
+    let cbor = to_cbor(&UpstreamSink::Pipe("p".into()));
+    assert_eq!(
+        from_cbor::<Sink>(&cbor),
+        Sink::Unknown {
+            name: "Pipe".into(),
+            target: Some("p".into())
+        }
+    );
+
+    // Test an adjacently tagged enum, with the content before or after the tag
+    assert_eq!(
+        from_str::<Hook>(r#"{"kind":"star","data":5}"#).unwrap(),
+        Hook::Star(5)
+    );
+    assert_eq!(
+        from_str::<Hook>(r#"{"data":{"repo":"r"},"kind":"push"}"#).unwrap(),
+        Hook::Push {
+            repo: "r".to_string()
+        }
+    );
+    assert_eq!(to_string(&Hook::Ping).unwrap(), r#"{"kind":"ping"}"#);
+    let unknown = from_str::<Hook>(r#"{"data":{"id":3},"kind":"fork"}"#).unwrap();
+    assert_eq!(unknown.as_str(), "fork");
+    assert_eq!(
+        to_string(&unknown).unwrap(),
+        r#"{"kind":"fork","data":{"id":3}}"#
+    );
+
+    // Test a format-independent payload, read back later as a typed value
+    let unknown = from_str::<Command>(r#"{"Move":[1,2]}"#).unwrap();
+    assert_eq!(to_string(&unknown).unwrap(), r#"{"Move":[1,2]}"#);
+    let Command::Unknown { name, args } = unknown else {
+        panic!("expected the catch-all");
+    };
+    assert_eq!(name, "Move");
+    assert_eq!(
+        <(u8, u8)>::deserialize(args.into_deserializer::<serde_json::Error>()).unwrap(),
+        (1, 2)
+    );
+    assert_eq!(
+        from_str::<Command>(r#""Look""#).unwrap(),
+        Command::Unknown {
+            name: "Look".to_string(),
+            args: Content::Unit
+        }
+    );
+    assert_eq!(
+        to_string(&Command::Echo("hi".into())).unwrap(),
+        r#"{"Echo":"hi"}"#
+    );
+    #[derive(Serialize)]
+    enum Shell {
+        Launch { target: String, args: Vec<u8> },
+    }
+    let launch = Shell::Launch {
+        target: "t".into(),
+        args: vec![1, 2],
+    };
+    let cbor = to_cbor(&launch);
+    let unknown = from_cbor::<Command>(&cbor);
+    assert_eq!(unknown.as_str(), "Launch");
+    assert_eq!(to_cbor(&unknown), cbor);
+    let msgpack = rmp_serde::to_vec_named(&launch).unwrap();
+    let unknown = rmp_serde::from_slice::<Command>(&msgpack).unwrap();
+    assert_eq!(unknown.as_str(), "Launch");
+    assert_eq!(rmp_serde::to_vec_named(&unknown).unwrap(), msgpack);
+
+    // Test strict mode, which rejects unknown values with a suggestion
+    assert_eq!(from_str::<Status>(r#""active""#).unwrap(), Status::Active);
+    assert_eq!(
+        from_str::<Status>(r#""actve""#).unwrap_err().to_string(),
+        r#"unknown variant "actve", did you mean "active"? at line 1 column 7"#
+    );
+    assert_eq!(
+        "retired".parse::<Status>().unwrap(),
+        Status::Other("retired".to_string())
+    );
+
+    // Test observers, told about unknown values as they are deserialized
+    serde_catch_all::set_observer(record_unknown);
+    from_str::<Example>(r#""Mystery""#).unwrap();
+    from_str::<Example>(r#""OptionA""#).unwrap();
+    from_str::<Size>(r#""Huge""#).unwrap();
+    from_str::<Hook>(r#"{"kind":"retry","data":3}"#).unwrap();
+    from_str::<Lossless<Example>>(r#""Enigma""#).unwrap();
+    serde_catch_all::clear_observer();
+    from_str::<Example>(r#""Ignored""#).unwrap();
+    assert_eq!(
+        *UNKNOWN_SEEN.lock().unwrap(),
+        ["Example: Mystery", "Hook: retry", "Example: Enigma"]
+    );
+    assert_eq!(UNKNOWN_SIZES.load(Ordering::Relaxed), 1);
+
+    // Test tracking where in a document unknown values show up
+    {
+        #[derive(Deserialize)]
+        struct Order {
+            status: Status,
+            size: Lossless<Size>,
+        }
+
+        let json = r#"[{"status":"active","size":"Small"},{"status":"inactive","size":"Tiny"}]"#;
+        let mut found = Vec::new();
+        let orders: Vec<Order> = serde_catch_all::track(
+            &mut serde_json::Deserializer::from_str(json),
+            |path, enum_name, raw| found.push(format!("{} = {:?} ({})", path, raw, enum_name)),
+        )
+        .unwrap();
+        assert_eq!(orders[1].status, Status::Inactive);
+        assert_eq!(*orders[1].size, Size::Other("Tiny".into()));
+        assert_eq!(found, [r#"[1].size = "Tiny" (Size)"#]);
+
+        // Payloads buffered before the variant is known keep their own path
+        #[serde_catch_all(tag = "type")]
+        enum Internal {
+            A {
+                size: Size,
+            },
+            #[serde(other)]
+            Unknown,
+        }
+
+        #[serde_catch_all(tag = "type", content = "data")]
+        enum Adjacent {
+            A {
+                size: Size,
+            },
+            #[serde(other)]
+            Unknown,
+        }
+
+        #[serde_catch_all]
+        enum External {
+            A {
+                size: Size,
+            },
+            #[serde(other)]
+            Unknown,
+        }
+
+        #[derive(Deserialize)]
+        struct Events {
+            internal: Vec<Internal>,
+            adjacent: Adjacent,
+            external: External,
+        }
+
+        let json = r#"{
+            "internal": [{"type": "A", "size": "Big"}],
+            "adjacent": {"data": {"size": "Bigger"}, "type": "A"},
+            "external": {"A": {"size": "Biggest"}}
+        }"#;
+        let mut found = Vec::new();
+        let events: Events = serde_catch_all::track(
+            &mut serde_json::Deserializer::from_str(json),
+            |path, _, raw| found.push(format!("{} = {}", path, raw)),
+        )
+        .unwrap();
+        assert!(matches!(
+            events.internal[..],
+            [Internal::A {
+                size: Size::Other(_)
+            }]
+        ));
+        assert!(matches!(
+            events.adjacent,
+            Adjacent::A {
+                size: Size::Other(_)
+            }
+        ));
+        assert!(matches!(
+            events.external,
+            External::A {
+                size: Size::Other(_)
+            }
+        ));
+        assert_eq!(
+            found,
+            [
+                "internal[0].size = Big",
+                "adjacent.data.size = Bigger",
+                "external.A.size = Biggest"
+            ]
+        );
+    }
+
+    // Test a struct keeping its unknown fields
+    let user = from_str::<User>(r#"{"plan":"pro","userId":5}"#).unwrap();
+    assert_eq!(user.user_id, 5);
+    assert_eq!(user.display_name, None);
+    assert_eq!(user.extra["plan"], "pro");
+    assert_eq!(
+        to_string(&user).unwrap(),
+        r#"{"userId":5,"displayName":null,"plan":"pro"}"#
+    );
+
+    // Test a raw JSON payload, forwarded without being reformatted
+    #[cfg(feature = "serde_json")]
+    {
+        let routed = from_str::<Routed>(r#"{"data":{ "id" : 3 },"kind":"fork"}"#).unwrap();
+        let Routed::Unknown { data, .. } = &routed else {
+            panic!("expected the catch-all");
+        };
+        assert_eq!(data.get(), r#"{ "id" : 3 }"#);
+        assert_eq!(
+            to_string(&routed).unwrap(),
+            r#"{"kind":"fork","data":{ "id" : 3 }}"#
+        );
+    }
+
     println!("All tests passed! The proc macro is working correctly.");
 }
@@ -0,0 +1,2025 @@
+mod case;
+mod normalize;
+mod options;
+mod structs;
+mod tagged;
+
+use case::RenameRule;
+use normalize::Normalizer;
+use options::{ContainerOptions, SerializeAs};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DataEnum, DeriveInput, Expr, ExprLit, Fields, Lit, Meta,
+    MetaNameValue, Path, Variant,
+};
+
+/// Attribute on enum: `#[serde_catch_all]`
+/// Within the enum, mark the catch-all variant: `#[catch_all]`, or serde's own `#[serde(other)]`
+/// The catch-all variant keeps the unknown name in a single string field, or drops it as a unit
+/// variant. On a struct, a `#[catch_all]` map field collects the unknown keys instead.
+///
+/// Supports serde's `rename`, `rename_all`, `alias` and `skip` attributes, integer
+/// discriminants, and tagged enums with data. The options, such as
+/// `#[serde_catch_all(tag = "type", strict)]`, are described in the crate's README.
+#[proc_macro_attribute]
+pub fn serde_catch_all(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let attr = proc_macro2::TokenStream::from(attr);
+    expand(&input, attr, Emit::ALL)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the serializing half of `#[serde_catch_all]`: `Serialize` and, where the attribute
+/// would add them, `as_str`, `Display`, `AsRef<str>` and `From<Enum> for String`.
+///
+/// Unlike the attribute, the derives leave the item as written, with `#[catch_all]` and
+/// `#[serde(...)]` registered as helper attributes. Options go in `#[catch_all(...)]` on the item
+/// itself, e.g. `#[catch_all(tag = "type", strict)]`, and the item must be valid Rust as it
+/// stands: discriminants next to a tuple catch-all need a `#[repr]`, and the catch-all of a strict
+/// enum, which deserializing never builds, may need an `#[allow(dead_code)]`.
+#[proc_macro_derive(CatchAllSerialize, attributes(catch_all, serde))]
+pub fn derive_serialize(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    derive(&input, Emit::SERIALIZE)
+}
+
+/// Derives the deserializing half of `#[serde_catch_all]`: `Deserialize`, the string
+/// conversions into the enum, `known_variants` and the introspection items.
+///
+/// See `CatchAllSerialize` for how the derives differ from the attribute.
+#[proc_macro_derive(CatchAllDeserialize, attributes(catch_all, serde))]
+pub fn derive_deserialize(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    derive(&input, Emit::DESERIALIZE)
+}
+
+/// Which parts of the expansion to emit.
+#[derive(Copy, Clone)]
+struct Emit {
+    /// The item itself, with the attributes only the macro understands removed.
+    item: bool,
+    serialize: bool,
+    deserialize: bool,
+}
+
+impl Emit {
+    const ALL: Emit = Emit {
+        item: true,
+        serialize: true,
+        deserialize: true,
+    };
+    const SERIALIZE: Emit = Emit {
+        item: false,
+        serialize: true,
+        deserialize: false,
+    };
+    const DESERIALIZE: Emit = Emit {
+        item: false,
+        serialize: false,
+        deserialize: true,
+    };
+    // Narrows the attribute's expansion down to `serialize_only` or `deserialize_only`. The
+    // derives already pick one direction each.
+    fn narrow(self, only: Option<&syn::Ident>) -> syn::Result<Emit> {
+        let Some(only) = only else {
+            return Ok(self);
+        };
+        if !self.item {
+            return Err(syn::Error::new_spanned(
+                only,
+                format!(
+                    "`{}` only applies to #[serde_catch_all], derive just the direction you need instead",
+                    only
+                ),
+            ));
+        }
+        Ok(Emit {
+            serialize: only == "serialize_only",
+            deserialize: only == "deserialize_only",
+            ..self
+        })
+    }
+}
+
+// The derives take their options from `#[catch_all(...)]` on the item rather than from the
+// attribute's arguments.
+fn derive(input: &DeriveInput, emit: Emit) -> TokenStream {
+    let mut options = proc_macro2::TokenStream::new();
+    for attr in input.attrs.iter().filter(|attr| is_catch_all_attr(attr)) {
+        match &attr.meta {
+            Meta::Path(_) => {}
+            Meta::List(list) => {
+                if !options.is_empty() {
+                    options.extend(quote! { , });
+                }
+                options.extend(list.tokens.clone());
+            }
+            Meta::NameValue(_) => {
+                return syn::Error::new_spanned(attr, "expected `#[catch_all(...)]`")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+    expand(input, options, emit)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(
+    input: &DeriveInput,
+    options: proc_macro2::TokenStream,
+    emit: Emit,
+) -> syn::Result<proc_macro2::TokenStream> {
+    if let Data::Struct(data) = &input.data {
+        let options = ContainerOptions::parse_struct(options)?;
+        return structs::expand(input, data, emit.narrow(options.only.as_ref())?);
+    }
+    let options = ContainerOptions::parse(options)?;
+    let emit = emit.narrow(options.only.as_ref())?;
+
+    let enum_ident = &input.ident;
+    let generics = &input.generics;
+
+    let data_enum = match &input.data {
+        Data::Enum(de) => de,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                if emit.item {
+                    "#[serde_catch_all] can only be applied to enums and structs"
+                } else {
+                    "serde_catch_all's derives can only be applied to enums and structs"
+                },
+            ));
+        }
+    };
+
+    let EnumInfo {
+        known_variants,
+        catch_all_variant_path,
+        catch_all_ty,
+        catch_all_borrow,
+        catch_all_unit,
+        code_catch_all,
+        catch_all_member,
+        catch_all_rest,
+        numeric,
+        has_data,
+    } = analyze_enum(enum_ident, &input.attrs, data_enum, &options, emit)?;
+
+    let payload = match catch_all_unit {
+        Some(_) => Payload::Unit,
+        None => Payload::classify(&catch_all_ty, catch_all_borrow),
+    };
+    let enum_vis = &input.vis;
+
+    // The derives leave the item alone, so only the attribute emits it
+    let cleaned_input = emit.item.then(|| clean_enum(input, &options, emit));
+
+    // Introspection tables, keyed by the serialize name as the canonical spelling. Variants
+    // skipped both ways never appear in a document, so they have no name to list.
+    let known_names = known_variants
+        .iter()
+        .filter(|v| v.is_listed())
+        .map(|v| &v.serialize_name);
+    let alias_pairs = known_variants.iter().flat_map(|v| {
+        let canonical = &v.serialize_name;
+        v.accepted_names()
+            .into_iter()
+            .filter(move |accepted| accepted.value != canonical.value)
+            .map(move |accepted| quote! { (#accepted, #canonical) })
+    });
+
+    let unknown_paths = std::iter::once(&catch_all_variant_path)
+        .chain(code_catch_all.as_ref().map(|(path, _)| path));
+    let introspection = quote! {
+        /// The serialized name of every known variant, in declaration order.
+        #enum_vis const KNOWN_NAMES: &'static [&'static str] = &[#(#known_names),*];
+
+        /// Every other accepted spelling, as `(accepted, serialized name)` pairs: the
+        /// `#[serde(alias)]`es, plus deserialize-only names from a split rename.
+        #enum_vis const ALIASES: &'static [(&'static str, &'static str)] = &[#(#alias_pairs),*];
+
+        /// Whether this is one of the named variants rather than the catch-all.
+        #enum_vis fn is_known(&self) -> bool {
+            !self.is_unknown()
+        }
+
+        /// Whether this value landed in the catch-all variant.
+        #enum_vis fn is_unknown(&self) -> bool {
+            ::core::matches!(self, #(#unknown_paths { .. })|*)
+        }
+    };
+
+    if has_data {
+        return Ok(tagged::expand(tagged::TaggedEnum {
+            input,
+            cleaned_input,
+            options: &options,
+            tag: options.tag.as_ref(),
+            content: options.content.as_ref(),
+            known_variants: &known_variants,
+            catch_all_path: &catch_all_variant_path,
+            catch_all_ty: &catch_all_ty,
+            name_member: &catch_all_member,
+            catch_all_unit: catch_all_unit.as_ref(),
+            catch_all_rest: catch_all_rest.as_ref(),
+            introspection,
+            emit,
+        }));
+    }
+
+    // Listed in the same order and with the same variants as `KNOWN_NAMES`
+    let known_paths = known_variants
+        .iter()
+        .filter(|v| v.is_listed())
+        .map(|v| &v.path);
+
+    // The lookup from a name to its variant lives in the generated `__serde_catch_all_known`,
+    // which serde and the `From`/`FromStr` impls share
+    let known_lookup_body = lookup_body(&known_variants, &options.normalizers, |_, v| {
+        let path = &v.path;
+        quote! { #path }
+    });
+
+    // `as_str` arms use the serialize name (rename if present, else rename_all applied to
+    // ident), and the Serialize, Display and AsRef impls all go through `as_str`
+    let as_str_arms = known_variants
+        .iter()
+        .map(|v| {
+            let name = &v.serialize_name;
+            let path = &v.path;
+            quote! { #path => #name, }
+        })
+        .collect::<Vec<_>>();
+
+    let catch_all_path = &catch_all_variant_path;
+    let code_catch_all_path = code_catch_all.as_ref().map(|(path, _)| path);
+
+    // The catch-all reads as its payload, or a unit catch-all as its own name
+    let (catch_all_pat, catch_all_str) = match &catch_all_unit {
+        Some(name) => (quote! { #catch_all_path }, quote! { #name }),
+        None => (
+            quote! { #catch_all_path(s) },
+            quote! { ::core::convert::AsRef::<str>::as_ref(s) },
+        ),
+    };
+
+    // How an unknown string `v` becomes the payload depends on whether it is a transient `&str`,
+    // an owned `String` or a `&'a str` the payload may keep borrowing. Not every payload can be
+    // built from every form: `&'a str` can only borrow.
+    let (catch_all_from_str, catch_all_from_string, catch_all_from_borrowed) = match payload {
+        Payload::Owned => (
+            Some(
+                quote! { #catch_all_path(::core::convert::From::from(::serde_catch_all::__private::ToOwned::to_owned(v))) },
+            ),
+            Some(quote! { #catch_all_path(::core::convert::From::from(v)) }),
+            None,
+        ),
+        Payload::OwnedFromStr => (
+            Some(quote! { #catch_all_path(::core::convert::From::from(v)) }),
+            Some(quote! { #catch_all_path(::core::convert::From::from(v)) }),
+            None,
+        ),
+        Payload::BorrowedStr => (None, None, Some(quote! { #catch_all_path(v) })),
+        Payload::Unit => (
+            Some(quote! { #catch_all_path }),
+            Some(quote! { #catch_all_path }),
+            None,
+        ),
+        Payload::Cow => (
+            Some(
+                quote! { #catch_all_path(::serde_catch_all::__private::Cow::Owned(::serde_catch_all::__private::ToOwned::to_owned(v))) },
+            ),
+            Some(quote! { #catch_all_path(::serde_catch_all::__private::Cow::Owned(v)) }),
+            Some(quote! { #catch_all_path(::serde_catch_all::__private::Cow::Borrowed(v)) }),
+        ),
+    };
+
+    // We implement both Deserialize and Serialize to make it round-trip.
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let known_lookup = quote! { <#enum_ident #ty_generics>::__serde_catch_all_known };
+
+    // Strict enums reject unknown values instead, suggesting the closest accepted names
+    let strict = options.is_strict();
+    let observe = |value| observe_unknown(&options, enum_ident, value);
+    let observer_check = observer_check(&options);
+    let skipped_serializing_check = skipped_serializing_check(enum_ident, &known_variants);
+    let visit_str_unknown = match &catch_all_from_str {
+        _ if strict => unknown_variant_error(&known_variants, quote! { v }, quote! { __E }),
+        Some(ctor) => {
+            let observe = observe(quote! { v });
+            quote! {{
+                #observe
+                ::core::result::Result::Ok(#ctor)
+            }}
+        }
+        None => quote! {
+            ::core::result::Result::Err(__E::invalid_type(::serde::de::Unexpected::Str(v), &self))
+        },
+    };
+    let visit_string_unknown = match &catch_all_from_string {
+        _ if strict => unknown_variant_error(&known_variants, quote! { &v }, quote! { __E }),
+        Some(ctor) => {
+            let observe = observe(quote! { &v });
+            quote! {{
+                #observe
+                ::core::result::Result::Ok(#ctor)
+            }}
+        }
+        None => quote! {
+            ::core::result::Result::Err(__E::invalid_type(::serde::de::Unexpected::Str(&v), &self))
+        },
+    };
+
+    // Owned payloads gain nothing from a borrowed string, so defer to `visit_str`
+    let visit_borrowed_str_body = match &catch_all_from_borrowed {
+        Some(ctor) if !strict => {
+            let observe = observe(quote! { v });
+            quote! {
+                match #known_lookup(v) {
+                    ::core::option::Option::Some(known) => ::core::result::Result::Ok(known),
+                    ::core::option::Option::None => {
+                        #observe
+                        ::core::result::Result::Ok(#ctor)
+                    }
+                }
+            }
+        }
+        _ => quote! { self.visit_str(v) },
+    };
+
+    // Integer input goes through the discriminant lookup, and unknown codes into the numeric
+    // catch-all when there is one and the value fits its payload
+    let code_arms = known_variants
+        .iter()
+        .filter(|v| !v.skip_deserializing)
+        .map(|v| {
+            let code = proc_macro2::Literal::i128_unsuffixed(v.code);
+            let path = &v.path;
+            quote! { #code => ::core::option::Option::Some(#path), }
+        });
+    let known_code_lookup = quote! { <#enum_ident #ty_generics>::__serde_catch_all_known_code };
+    let visit_code = |unexpected: proc_macro2::TokenStream| {
+        let unknown = match code_catch_all_path {
+            Some(path) if !strict => quote! {
+                ::core::convert::TryFrom::try_from(v)
+                    .map(#path)
+                    .map_err(|_| __E::invalid_value(::serde::de::Unexpected::#unexpected(v), &self))
+            },
+            _ => quote! {
+                ::core::result::Result::Err(__E::invalid_value(::serde::de::Unexpected::#unexpected(v), &self))
+            },
+        };
+        quote! {
+            match #known_code_lookup(::core::convert::From::from(v)) {
+                ::core::option::Option::Some(known) => ::core::result::Result::Ok(known),
+                ::core::option::Option::None => #unknown,
+            }
+        }
+    };
+    let visit_i64_body = visit_code(quote! { Signed });
+    let visit_u64_body = visit_code(quote! { Unsigned });
+    // Formats that are not self-describing cannot tell a name from a code on their own. When
+    // the enum is marked for them, those that write both, because a catch-all is a string or the
+    // numeric catch-all is not, write a newtype variant there saying which one follows.
+    let enum_name = enum_ident.unraw().to_string();
+    let compact = numeric
+        && options.non_self_describing
+        && (options.serialize_as != SerializeAs::Name || code_catch_all_path.is_some());
+    let (code_lookup_fn, visit_codes, expecting, deserialize_call) = if numeric {
+        // Self-describing formats say which one they hold, while the others need a hint
+        let deserialize_call = if !options.non_self_describing {
+            quote! { deserializer.deserialize_any(visitor) }
+        } else {
+            let compact_call = if compact {
+                quote! { ::serde_catch_all::__private::deserialize_compact(deserializer, #enum_name, visitor) }
+            } else {
+                quote! { deserializer.deserialize_str(visitor) }
+            };
+            quote! {
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_any(visitor)
+                } else {
+                    #compact_call
+                }
+            }
+        };
+        (
+            Some(quote! {
+                fn __serde_catch_all_known_code(v: i128) -> ::core::option::Option<Self> {
+                    match v {
+                        #(#code_arms)*
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }),
+            Some(quote! {
+                fn visit_i64<__E>(self, v: i64) -> ::core::result::Result<Self::Value, __E>
+                where
+                    __E: ::serde::de::Error,
+                {
+                    #visit_i64_body
+                }
+
+                fn visit_u64<__E>(self, v: u64) -> ::core::result::Result<Self::Value, __E>
+                where
+                    __E: ::serde::de::Error,
+                {
+                    #visit_u64_body
+                }
+            }),
+            "a string or integer enum",
+            deserialize_call,
+        )
+    } else {
+        (
+            None,
+            None,
+            "a string enum",
+            quote! { deserializer.deserialize_str(visitor) },
+        )
+    };
+
+    // Known variants are written as their name or discriminant. The string catch-all writes the
+    // captured string and the numeric one its code.
+    let serialize_body = if numeric {
+        let number_compact = options.serialize_as != SerializeAs::Name;
+        let mut name_arms = Vec::new();
+        let mut code_arms = Vec::new();
+        let mut compact_arms = Vec::new();
+        for v in &known_variants {
+            let name = &v.serialize_name;
+            let path = &v.path;
+            let code = match i64::try_from(v.code) {
+                Ok(code) => proc_macro2::Literal::i64_suffixed(code),
+                Err(_) => proc_macro2::Literal::u64_suffixed(v.code as u64),
+            };
+            name_arms.push(quote! { #path => serializer.serialize_str(#name), });
+            code_arms.push(quote! { #path => ::serde::Serialize::serialize(&#code, serializer), });
+            compact_arms.push(if number_compact {
+                quote! { #path => ::serde_catch_all::__private::serialize_compact_code(serializer, #enum_name, #code), }
+            } else {
+                quote! { #path => ::serde_catch_all::__private::serialize_compact_name(serializer, #enum_name, #name), }
+            });
+        }
+        let code_arm = code_catch_all_path.map(|path| {
+            quote! { #path(code) => ::serde::Serialize::serialize(code, serializer), }
+        });
+        let by_name = quote! {
+            match self {
+                #(#name_arms)*
+                #catch_all_pat => serializer.serialize_str(#catch_all_str),
+                #code_arm
+            }
+        };
+        let by_code = quote! {
+            match self {
+                #(#code_arms)*
+                #catch_all_pat => serializer.serialize_str(#catch_all_str),
+                #code_arm
+            }
+        };
+        let human_readable = match options.serialize_as {
+            SerializeAs::Number => &by_code,
+            SerializeAs::Name | SerializeAs::NumberIfBinary => &by_name,
+        };
+        let binary = if compact {
+            let compact_code_arm = code_catch_all_path.map(|path| {
+                quote! {
+                    #path(code) => ::serde_catch_all::__private::serialize_compact_code(serializer, #enum_name, *code),
+                }
+            });
+            Some(quote! {
+                match self {
+                    #(#compact_arms)*
+                    #catch_all_pat => ::serde_catch_all::__private::serialize_compact_name(serializer, #enum_name, #catch_all_str),
+                    #compact_code_arm
+                }
+            })
+        } else if options.serialize_as == SerializeAs::NumberIfBinary {
+            Some(by_code.clone())
+        } else {
+            None
+        };
+        match binary {
+            Some(binary) => quote! {
+                if serializer.is_human_readable() {
+                    #human_readable
+                } else {
+                    #binary
+                }
+            },
+            None => human_readable.clone(),
+        }
+    } else {
+        quote! { serializer.serialize_str(self.as_str()) }
+    };
+
+    // The catch-all payload is built from the unknown string and viewed as `&str` when
+    // serializing, so each direction gets the bound it needs on the payload type. Spanning the
+    // bounds on the field type points an unsupported payload's error at the field.
+    let mut de_where_clause = generics
+        .where_clause
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote! { where });
+    let mut ser_where_clause = de_where_clause.clone();
+    if payload != Payload::Unit && !is_string_type(&catch_all_ty) {
+        let span = catch_all_ty.span();
+        match payload {
+            Payload::Owned => de_where_clause.predicates.push(syn::parse_quote_spanned! {span=>
+                #catch_all_ty: ::core::convert::From<::serde_catch_all::__private::String>
+            }),
+            Payload::OwnedFromStr => de_where_clause.predicates.push(syn::parse_quote_spanned! {span=>
+                #catch_all_ty: for<'__s> ::core::convert::From<&'__s str> + ::core::convert::From<::serde_catch_all::__private::String>
+            }),
+            Payload::BorrowedStr | Payload::Cow | Payload::Unit => {}
+        }
+        ser_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #catch_all_ty: ::core::convert::AsRef<str>
+            });
+    }
+
+    // Deserialize needs the extra `'de` lifetime in front of the enum's own parameters, and it
+    // must outlive whatever lifetime a borrowed payload holds on to
+    let borrowed_lifetimes = match payload {
+        Payload::BorrowedStr | Payload::Cow => borrowed_lifetimes(&catch_all_ty),
+        Payload::Owned | Payload::OwnedFromStr | Payload::Unit => Vec::new(),
+    };
+    let mut de_lifetime: syn::LifetimeParam = syn::parse_quote! { 'de };
+    de_lifetime
+        .bounds
+        .extend(borrowed_lifetimes.iter().cloned());
+    let mut de_generics = generics.clone();
+    de_generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(de_lifetime));
+    let (de_impl_generics, de_ty_generics, _) = de_generics.split_for_impl();
+
+    // Without a numeric catch-all every value has a name, so the string views go through
+    // `as_str`. Otherwise `Display` writes unknown codes as numbers and there is no `as_str`.
+    let (as_str_impl, display_body, as_ref_impl, into_string_body) = match code_catch_all_path {
+        None => (
+            Some(quote! {
+                impl #impl_generics #enum_ident #ty_generics #ser_where_clause {
+                    /// Returns the name this value is serialized as, which for the catch-all variant is
+                    /// the captured string.
+                    #enum_vis fn as_str(&self) -> &str {
+                        match self {
+                            #(#as_str_arms)*
+                            #catch_all_pat => #catch_all_str,
+                        }
+                    }
+                }
+            }),
+            quote! { f.write_str(self.as_str()) },
+            Some(quote! {
+                impl #impl_generics ::core::convert::AsRef<str> for #enum_ident #ty_generics #ser_where_clause {
+                    fn as_ref(&self) -> &str {
+                        self.as_str()
+                    }
+                }
+            }),
+            quote! { ::serde_catch_all::__private::String::from(v.as_str()) },
+        ),
+        Some(code_path) => (
+            None,
+            quote! {
+                let name: &str = match self {
+                    #(#as_str_arms)*
+                    #catch_all_pat => #catch_all_str,
+                    #code_path(code) => return ::core::fmt::Display::fmt(code, f),
+                };
+                f.write_str(name)
+            },
+            None,
+            quote! { ::serde_catch_all::__private::ToString::to_string(&v) },
+        ),
+    };
+
+    // `From<&str>` keeps borrowing when the payload can, otherwise it copies like `FromStr`
+    let from_str_impl = match (&catch_all_from_borrowed, &catch_all_from_str) {
+        (Some(ctor), _) => {
+            let lifetime = &borrowed_lifetimes[0];
+            Some((quote! { &#lifetime str }, ctor))
+        }
+        (None, Some(ctor)) => Some((quote! { &str }, ctor)),
+        (None, None) => None,
+    }
+    .map(|(str_ty, ctor)| {
+        quote! {
+            impl #impl_generics ::core::convert::From<#str_ty> for #enum_ident #ty_generics #de_where_clause {
+                fn from(v: #str_ty) -> Self {
+                    match #known_lookup(v) {
+                        ::core::option::Option::Some(known) => known,
+                        ::core::option::Option::None => #ctor,
+                    }
+                }
+            }
+        }
+    });
+
+    let from_string_impl = catch_all_from_string.as_ref().map(|ctor| {
+        quote! {
+            impl #impl_generics ::core::convert::From<::serde_catch_all::__private::String> for #enum_ident #ty_generics #de_where_clause {
+                fn from(v: ::serde_catch_all::__private::String) -> Self {
+                    match #known_lookup(&v) {
+                        ::core::option::Option::Some(known) => known,
+                        ::core::option::Option::None => #ctor,
+                    }
+                }
+            }
+        }
+    });
+
+    let parse_impl = catch_all_from_str.as_ref().map(|ctor| {
+        quote! {
+            impl #impl_generics ::core::str::FromStr for #enum_ident #ty_generics #de_where_clause {
+                type Err = ::core::convert::Infallible;
+
+                fn from_str(v: &str) -> ::core::result::Result<Self, Self::Err> {
+                    ::core::result::Result::Ok(match #known_lookup(v) {
+                        ::core::option::Option::Some(known) => known,
+                        ::core::option::Option::None => #ctor,
+                    })
+                }
+            }
+        }
+    });
+
+    // The introspection items belong to the item rather than either direction, so only the
+    // serializing derive leaves them out
+    let introspection = (emit.item || emit.deserialize).then(|| {
+        quote! {
+            impl #impl_generics #enum_ident #ty_generics #where_clause {
+                #introspection
+
+                /// Iterates over every known variant that is not skipped both ways, in declaration
+                /// order.
+                #enum_vis fn known_variants() -> impl ::core::iter::Iterator<Item = Self> {
+                    [#(#known_paths),*].into_iter()
+                }
+            }
+        }
+    });
+
+    let deserialize = emit.deserialize.then(|| quote! {
+        impl #impl_generics #enum_ident #ty_generics #where_clause {
+            fn __serde_catch_all_known(v: &str) -> ::core::option::Option<Self> {
+                #known_lookup_body
+            }
+
+            #code_lookup_fn
+        }
+
+        impl #de_impl_generics ::serde::Deserialize<'de> for #enum_ident #ty_generics #de_where_clause {
+            fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                struct __Visitor #de_impl_generics #de_where_clause {
+                    marker: ::core::marker::PhantomData<#enum_ident #ty_generics>,
+                    lifetime: ::core::marker::PhantomData<&'de ()>,
+                }
+
+                impl #de_impl_generics ::serde::de::Visitor<'de> for __Visitor #de_ty_generics #de_where_clause {
+                    type Value = #enum_ident #ty_generics;
+
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_str<__E>(self, v: &str) -> ::core::result::Result<Self::Value, __E>
+                    where
+                        __E: ::serde::de::Error,
+                    {
+                        match #known_lookup(v) {
+                            ::core::option::Option::Some(known) => ::core::result::Result::Ok(known),
+                            ::core::option::Option::None => #visit_str_unknown,
+                        }
+                    }
+
+                    fn visit_borrowed_str<__E>(self, v: &'de str) -> ::core::result::Result<Self::Value, __E>
+                    where
+                        __E: ::serde::de::Error,
+                    {
+                        #visit_borrowed_str_body
+                    }
+
+                    fn visit_string<__E>(self, v: ::serde_catch_all::__private::String) -> ::core::result::Result<Self::Value, __E>
+                    where
+                        __E: ::serde::de::Error,
+                    {
+                        match #known_lookup(&v) {
+                            ::core::option::Option::Some(known) => ::core::result::Result::Ok(known),
+                            ::core::option::Option::None => #visit_string_unknown,
+                        }
+                    }
+
+                    #visit_codes
+                }
+
+                let visitor = __Visitor {
+                    marker: ::core::marker::PhantomData,
+                    lifetime: ::core::marker::PhantomData,
+                };
+                #deserialize_call
+            }
+        }
+
+        #from_str_impl
+        #from_string_impl
+        #parse_impl
+    });
+
+    let serialize = emit.serialize.then(|| quote! {
+        #as_str_impl
+
+        impl #impl_generics ::serde::Serialize for #enum_ident #ty_generics #ser_where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                #skipped_serializing_check
+                #serialize_body
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Display for #enum_ident #ty_generics #ser_where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                #display_body
+            }
+        }
+
+        #as_ref_impl
+
+        impl #impl_generics ::core::convert::From<#enum_ident #ty_generics> for ::serde_catch_all::__private::String #ser_where_clause {
+            fn from(v: #enum_ident #ty_generics) -> Self {
+                #into_string_body
+            }
+        }
+    });
+
+    Ok(quote! {
+        // Keep the user's enum but without problematic attributes
+        #cleaned_input
+        #observer_check
+        #introspection
+        #deserialize
+        #serialize
+    })
+}
+
+// Creates a clean version of the input enum without serde and catch_all attributes.
+fn clean_enum(input: &DeriveInput, options: &ContainerOptions, emit: Emit) -> DeriveInput {
+    let mut cleaned_input = input.clone();
+    cleaned_input
+        .attrs
+        .retain(|attr| !attr.path().is_ident("serde"));
+    // Rust only allows discriminants next to the tuple catch-all with a `#[repr]`, so without
+    // one they are only read by the macro and dropped from the emitted enum
+    let has_repr = input.attrs.iter().any(|attr| attr.path().is_ident("repr"));
+    if let Data::Enum(ref mut data_enum) = cleaned_input.data {
+        for variant in &mut data_enum.variants {
+            // Strict enums only build their catch-alls through the string conversions, if at all.
+            // Without one of the directions, the catch-all is never built or never read.
+            let one_sided = !(emit.serialize && emit.deserialize);
+            if (options.is_strict() || one_sided)
+                && matches!(is_catch_all_variant(variant), Ok(true))
+            {
+                variant
+                    .attrs
+                    .push(syn::parse_quote! { #[allow(dead_code)] });
+            }
+            variant
+                .attrs
+                .retain(|attr| !is_catch_all_attr(attr) && !attr.path().is_ident("serde"));
+            if !has_repr {
+                variant.discriminant = None;
+            }
+            for field in &mut variant.fields {
+                field.attrs.retain(|attr| !attr.path().is_ident("serde"));
+            }
+        }
+    }
+
+    cleaned_input
+}
+
+// Reports the unknown name `value` to the enum's own observer, or the global one without it,
+// and to a running `track` call.
+fn observe_unknown(
+    options: &ContainerOptions,
+    enum_ident: &syn::Ident,
+    value: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let enum_name = enum_ident.unraw().to_string();
+    match &options.observe {
+        Some(observer) => quote! {
+            ::serde_catch_all::__private::observe_unknown(
+                #enum_name,
+                #value,
+                ::core::option::Option::Some(#observer),
+            );
+        },
+        None => quote! {
+            ::serde_catch_all::__private::observe_unknown(
+                #enum_name,
+                #value,
+                ::core::option::Option::None,
+            );
+        },
+    }
+}
+
+// Checks the enum's own observer against the `Observer` signature up front, which also keeps it
+// in use for strict enums that never call it.
+fn observer_check(options: &ContainerOptions) -> Option<proc_macro2::TokenStream> {
+    options.observe.as_ref().map(|observer| {
+        quote::quote_spanned! {observer.span()=>
+            const _: ::serde_catch_all::Observer = #observer;
+        }
+    })
+}
+
+// Returns an error from `serialize` for the variants marked `#[serde(skip_serializing)]`, like
+// serde does.
+fn skipped_serializing_check(
+    enum_ident: &syn::Ident,
+    known_variants: &[KnownVariant],
+) -> Option<proc_macro2::TokenStream> {
+    let arms = known_variants
+        .iter()
+        .filter(|v| v.skip_serializing)
+        .map(|v| {
+            let path = &v.path;
+            let message = format!(
+                "the enum variant {}::{} cannot be serialized",
+                enum_ident.unraw(),
+                v.ident.unraw()
+            );
+            quote! {
+                #path { .. } => {
+                    return ::core::result::Result::Err(::serde::ser::Error::custom(#message));
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    (!arms.is_empty()).then(|| {
+        quote! {
+            match self {
+                #(#arms)*
+                _ => {}
+            }
+        }
+    })
+}
+
+// The error a strict enum gives for the unknown name `value`, as an `Err` of type `error`.
+fn unknown_variant_error(
+    known_variants: &[KnownVariant],
+    value: proc_macro2::TokenStream,
+    error: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let names = known_variants.iter().flat_map(KnownVariant::accepted_names);
+    quote! {
+        ::core::result::Result::Err(
+            ::serde_catch_all::__private::unknown_variant::<#error>(#value, &[#(#names),*]),
+        )
+    }
+}
+
+struct EnumInfo {
+    known_variants: Vec<KnownVariant>,
+    catch_all_variant_path: Path,
+    catch_all_ty: syn::Type,
+    catch_all_borrow: bool,
+    /// For a unit catch-all, which drops the unknown value, the name it is written as.
+    catch_all_unit: Option<Name>,
+    /// The catch-all for unknown discriminants, when one has an integer payload.
+    code_catch_all: Option<(Path, syn::Type)>,
+    /// The catch-all field holding the string, or the tag for tagged enums.
+    catch_all_member: syn::Member,
+    /// For tagged enums, the catch-all field holding everything besides the tag.
+    catch_all_rest: Option<(syn::Member, syn::Type)>,
+    /// Whether discriminants are accepted (and possibly written) besides names.
+    numeric: bool,
+    /// Whether the enum is tagged, internally or externally, rather than a plain string.
+    has_data: bool,
+}
+
+struct KnownVariant {
+    ident: syn::Ident,
+    path: Path,
+    serialize_name: Name,
+    deserialize_name: Name,
+    aliases: Vec<Name>,
+    code: i128,
+    shape: Shape,
+    /// `#[serde(skip_serializing)]`: writing this variant is an error.
+    skip_serializing: bool,
+    /// `#[serde(skip_deserializing)]`: no input matches this variant, so its name is unknown.
+    skip_deserializing: bool,
+}
+
+/// What a known variant holds besides its name.
+enum Shape {
+    Unit,
+    Newtype(syn::Type),
+    Tuple(Vec<syn::Type>),
+    Struct(Vec<StructField>),
+}
+
+struct StructField {
+    ident: syn::Ident,
+    name: Name,
+    deserialize_name: Name,
+    aliases: Vec<Name>,
+    ty: syn::Type,
+}
+
+impl StructField {
+    /// Reads a named field's names. The `rename_all` of a struct applies to its fields, while
+    /// those of enum variants keep their idents.
+    fn analyze(
+        field: &syn::Field,
+        rename_all: Option<&RenameAll>,
+        tag: Option<&syn::LitStr>,
+    ) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        check_serde_keys(&field.attrs, &["rename", "alias"])?;
+        let (rename, aliases) = extract_serde_names(&field.attrs)?;
+        let derived_name = |rule: Option<RenameRule>| Name {
+            value: rule
+                .unwrap_or(RenameRule::None)
+                .apply_to_field(&ident.unraw().to_string()),
+            span: ident.span(),
+        };
+        let name = rename
+            .serialize
+            .unwrap_or_else(|| derived_name(rename_all.map(|rules| rules.serialize)));
+        let deserialize_name = rename
+            .deserialize
+            .unwrap_or_else(|| derived_name(rename_all.map(|rules| rules.deserialize)));
+
+        if let Some(clash) = tag.and_then(|tag| {
+            std::iter::once(&deserialize_name)
+                .chain(&aliases)
+                .find(|name| name.value == tag.value())
+        }) {
+            return Err(syn::Error::new(
+                clash.span,
+                format!("field {:?} clashes with the tag of the enum", clash.value),
+            ));
+        }
+
+        Ok(StructField {
+            ident,
+            name,
+            deserialize_name,
+            aliases,
+            ty: field.ty.clone(),
+        })
+    }
+}
+
+impl KnownVariant {
+    /// Whether the variant can appear in a document at all, i.e. is not skipped both ways.
+    fn is_listed(&self) -> bool {
+        !(self.skip_serializing && self.skip_deserializing)
+    }
+
+    /// The deserialize name followed by the aliases, without repeats. None for a variant that
+    /// is skipped when deserializing.
+    fn accepted_names(&self) -> Vec<&Name> {
+        let mut names: Vec<&Name> = Vec::new();
+        if self.skip_deserializing {
+            return names;
+        }
+        for name in std::iter::once(&self.deserialize_name).chain(&self.aliases) {
+            if !names.iter().any(|seen| seen.value == name.value) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// The accepted names as they appear in the lookup's match, i.e. after the built-in
+    /// normalizers, without repeats. Custom normalizers leave the names as written.
+    fn matched_names(&self, normalizers: &[Normalizer]) -> Vec<Name> {
+        let mut names: Vec<Name> = Vec::new();
+        for name in self.accepted_names() {
+            let value = normalize::apply_all(normalizers, &name.value)
+                .unwrap_or_else(|| name.value.clone());
+            if !names.iter().any(|seen| seen.value == value) {
+                names.push(Name {
+                    value,
+                    span: name.span,
+                });
+            }
+        }
+        names
+    }
+}
+
+/// A wire name together with where it came from: the `rename`/`alias` literal, or the variant
+/// ident when the name was derived from it. Generated string literals keep that span.
+#[derive(Clone)]
+struct Name {
+    value: String,
+    span: proc_macro2::Span,
+}
+
+impl Name {
+    fn from_lit(lit: &syn::LitStr) -> Self {
+        Name {
+            value: lit.value(),
+            span: lit.span(),
+        }
+    }
+}
+
+impl quote::ToTokens for Name {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        syn::LitStr::new(&self.value, self.span).to_tokens(tokens);
+    }
+}
+
+fn analyze_enum(
+    enum_ident: &syn::Ident,
+    enum_attrs: &[Attribute],
+    de: &DataEnum,
+    options: &ContainerOptions,
+    emit: Emit,
+) -> syn::Result<EnumInfo> {
+    check_serde_keys(enum_attrs, &["rename_all"])?;
+    let rename_all = extract_rename_all(enum_attrs)?;
+    let mut known_variants = Vec::<KnownVariant>::new();
+    let mut catch_all: Option<(Path, syn::Type, bool)> = None;
+    let mut code_catch_all: Option<(Path, syn::Type)> = None;
+    let mut catch_all_variants = Vec::<&Variant>::new();
+    let mut catch_all_member = syn::Member::Unnamed(syn::Index::from(0));
+    let mut catch_all_rest: Option<(syn::Member, syn::Type)> = None;
+    let mut has_discriminant = false;
+    let mut next_code: i128 = 0;
+
+    for v in &de.variants {
+        let is_catch_all = is_catch_all_variant(v)?;
+
+        // Discriminants follow Rust's rules: explicit, or one more than the previous variant's
+        let code = match &v.discriminant {
+            Some((_, expr)) => {
+                has_discriminant = true;
+                discriminant_value(expr)?
+            }
+            None => next_code,
+        };
+        next_code = code + 1;
+
+        // Which catch-alls are allowed depends on whether the enum turns out to carry data
+        let (skip_serializing, skip_deserializing) = extract_skip(&v.attrs)?;
+        if is_catch_all {
+            if skip_serializing || skip_deserializing {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "the #[catch_all] variant cannot be skipped",
+                ));
+            }
+            check_serde_keys(&v.attrs, &["borrow", "other"])?;
+            for field in &v.fields {
+                check_serde_keys(&field.attrs, &[])?;
+            }
+            catch_all_variants.push(v);
+            continue;
+        }
+        check_serde_keys(
+            &v.attrs,
+            &[
+                "rename",
+                "alias",
+                "skip",
+                "skip_serializing",
+                "skip_deserializing",
+            ],
+        )?;
+
+        let shape = match &v.fields {
+            Fields::Unit => Shape::Unit,
+            Fields::Unnamed(un) => {
+                for field in &un.unnamed {
+                    check_serde_keys(&field.attrs, &[])?;
+                }
+                if un.unnamed.len() == 1 {
+                    Shape::Newtype(un.unnamed[0].ty.clone())
+                } else {
+                    Shape::Tuple(un.unnamed.iter().map(|f| f.ty.clone()).collect())
+                }
+            }
+            Fields::Named(named) => {
+                let fields = named
+                    .named
+                    .iter()
+                    .map(|field| StructField::analyze(field, None, options.internal_tag()))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                if emit.deserialize {
+                    check_field_names(&fields)?;
+                }
+                if emit.serialize {
+                    check_serialize_names(&fields)?;
+                }
+                Shape::Struct(fields)
+            }
+        };
+        if options.internal_tag().is_some() && matches!(shape, Shape::Tuple(_)) {
+            return Err(syn::Error::new_spanned(
+                v,
+                "variants of an internally tagged enum must be unit, newtype or struct variants",
+            ));
+        }
+
+        // Extract names and aliases
+        let (rename, aliases) = extract_serde_names(&v.attrs)?;
+        let ident = v.ident.to_string();
+        let derived_name = |rule: RenameRule| Name {
+            value: rule.apply_to_variant(&ident),
+            span: v.ident.span(),
+        };
+
+        // A variant-level rename wins over the container's rename_all, in each direction
+        let serialize_name = rename
+            .serialize
+            .unwrap_or_else(|| derived_name(rename_all.serialize));
+        let deserialize_name = rename
+            .deserialize
+            .unwrap_or_else(|| derived_name(rename_all.deserialize));
+
+        known_variants.push(KnownVariant {
+            ident: v.ident.clone(),
+            path: variant_path(enum_ident, v),
+            serialize_name,
+            deserialize_name,
+            aliases,
+            code,
+            shape,
+            skip_serializing,
+            skip_deserializing,
+        });
+    }
+
+    check_collisions(&known_variants, &options.normalizers, emit)?;
+
+    // Like serde, variants with data make the enum externally tagged unless it has a tag field.
+    // A catch-all with a second field for the payload asks for the same.
+    let has_data = options.tag.is_some()
+        || known_variants
+            .iter()
+            .any(|v| !matches!(v.shape, Shape::Unit))
+        || catch_all_variants.iter().any(|v| v.fields.len() > 1);
+
+    let mut catch_all_unit = None;
+    for v in catch_all_variants {
+        if has_data && !v.fields.is_empty() {
+            // The name, optionally followed by whatever came with it
+            let mut fields = v.fields.members().zip(v.fields.iter());
+            let (Some((name_member, name)), rest, None) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "the #[catch_all] variant of an enum with data must have a field for the name, optionally followed by one for the payload",
+                ));
+            };
+            if catch_all.is_some() {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "only one #[catch_all] variant is allowed",
+                ));
+            }
+            if extract_borrow(&v.attrs)? || is_borrowed_str_type(&name.ty) {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "the catch-all of an enum with data cannot borrow from the input",
+                ));
+            }
+            if let Some((_, rest)) = &rest {
+                if options.internal_tag().is_some() && tagged::raw_payload(&rest.ty).is_some() {
+                    return Err(syn::Error::new_spanned(
+                        &rest.ty,
+                        "a raw JSON payload needs an externally or adjacently tagged enum, as the rest of an internally tagged object is not one JSON value",
+                    ));
+                }
+            }
+            catch_all = Some((variant_path(enum_ident, v), name.ty.clone(), false));
+            catch_all_member = name_member;
+            catch_all_rest = rest.map(|(member, field)| (member, field.ty.clone()));
+            continue;
+        }
+
+        // Must be tuple variant with a single String, or a unit variant dropping the value
+        let ty = match &v.fields {
+            Fields::Unnamed(un) if un.unnamed.len() == 1 => un.unnamed[0].ty.clone(),
+            Fields::Unit => {
+                if catch_all.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        v,
+                        "only one #[catch_all] variant is allowed",
+                    ));
+                }
+                catch_all = Some((variant_path(enum_ident, v), syn::parse_quote! { () }, false));
+                catch_all_unit = Some(Name {
+                    value: rename_all.serialize.apply_to_variant(&v.ident.to_string()),
+                    span: v.ident.span(),
+                });
+                continue;
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "the #[catch_all] variant must be a tuple variant with exactly one field, such as `String`, or a unit variant that drops the value",
+                ));
+            }
+        };
+
+        // An integer payload catches unknown discriminants, anything else unknown names
+        if is_integer_type(&ty) {
+            if code_catch_all.is_some() {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "only one numeric #[catch_all] variant is allowed",
+                ));
+            }
+            code_catch_all = Some((variant_path(enum_ident, v), ty));
+            continue;
+        }
+
+        if catch_all.is_some() {
+            return Err(syn::Error::new_spanned(
+                v,
+                "only one #[catch_all] variant is allowed",
+            ));
+        }
+        let borrow = extract_borrow(&v.attrs)?;
+        catch_all = Some((variant_path(enum_ident, v), ty, borrow));
+    }
+
+    let (catch_all_variant_path, catch_all_ty, catch_all_borrow) = catch_all.ok_or_else(|| {
+        syn::Error::new_spanned(
+            enum_ident,
+            "you must provide exactly one #[catch_all] or #[serde(other)] variant with a single `String` field",
+        )
+    })?;
+    if emit.serialize {
+        if let Some(name) = &catch_all_unit {
+            check_unit_catch_all(&known_variants, &catch_all_variant_path, name)?;
+        }
+    }
+
+    let numeric =
+        has_discriminant || code_catch_all.is_some() || options.serialize_as != SerializeAs::Name;
+    if numeric {
+        if has_data {
+            return Err(syn::Error::new_spanned(
+                enum_ident,
+                "discriminants and `serialize_as` need an enum of unit variants without a tag",
+            ));
+        }
+        check_codes(&known_variants)?;
+    }
+
+    Ok(EnumInfo {
+        known_variants,
+        catch_all_variant_path,
+        catch_all_ty,
+        catch_all_borrow,
+        catch_all_unit,
+        code_catch_all,
+        catch_all_member,
+        catch_all_rest,
+        numeric,
+        has_data,
+    })
+}
+
+// Read an explicit discriminant, which has to be a plain (possibly negated) integer literal for
+// the macro to know its value.
+fn discriminant_value(expr: &Expr) -> syn::Result<i128> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => discriminant_value(expr).map(|value| -value),
+        Expr::Paren(paren) => discriminant_value(&paren.expr),
+        Expr::Group(group) => discriminant_value(&group.expr),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "#[serde_catch_all] only supports integer literal discriminants",
+        )),
+    }
+}
+
+// Without a `#[repr]` the discriminants are stripped from the emitted enum, so rustc no longer
+// catches duplicates, and serialized codes have to fit serde's `i64` or `u64`.
+fn check_codes(variants: &[KnownVariant]) -> syn::Result<()> {
+    let mut seen = std::collections::HashMap::<i128, &KnownVariant>::new();
+
+    for variant in variants {
+        if variant.code < i128::from(i64::MIN) || variant.code > i128::from(u64::MAX) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                format!(
+                    "discriminant {} of `{}` does not fit in an `i64` or `u64`",
+                    variant.code, variant.ident
+                ),
+            ));
+        }
+        if let Some(first) = seen.insert(variant.code, variant) {
+            let mut err = syn::Error::new_spanned(
+                &variant.ident,
+                format!(
+                    "variants `{}` and `{}` both have discriminant {}",
+                    first.ident, variant.ident, variant.code
+                ),
+            );
+            err.combine(syn::Error::new_spanned(
+                &first.ident,
+                format!("`{}` first uses {} here", first.ident, first.code),
+            ));
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+// The body of a generated `fn(v: &str) -> Option<_>` returning `value` for the variant the name
+// `v` belongs to. The match arms cover the deserialize names and aliases.
+fn lookup_body(
+    variants: &[KnownVariant],
+    normalizers: &[Normalizer],
+    value: impl Fn(usize, &KnownVariant) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let known_match_arms = variants.iter().enumerate().flat_map(|(index, v)| {
+        let value = value(index, v);
+        v.matched_names(normalizers)
+            .into_iter()
+            .map(move |name| quote! { #name => ::core::option::Option::Some(#value), })
+    });
+
+    // With normalizers, the input is normalized before matching. Built-in steps were already
+    // applied to the names above at compile time, but custom steps only exist at runtime, so the
+    // names have to go through them on every lookup instead of being matched as literals.
+    let normalized = normalize::runtime_expr(normalizers);
+    if normalizers.is_empty() {
+        quote! {
+            match v {
+                #(#known_match_arms)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    } else if normalize::apply_all(normalizers, "").is_some() {
+        quote! {
+            fn normalize(v: &str) -> ::serde_catch_all::__private::Cow<'_, str> {
+                #normalized
+            }
+
+            match &*normalize(v) {
+                #(#known_match_arms)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    } else {
+        let checks = variants.iter().enumerate().flat_map(|(index, v)| {
+            let value = value(index, v);
+            v.accepted_names().into_iter().map(move |name| {
+                quote! {
+                    if key == normalize(#name) {
+                        return ::core::option::Option::Some(#value);
+                    }
+                }
+            })
+        });
+        quote! {
+            fn normalize(v: &str) -> ::serde_catch_all::__private::Cow<'_, str> {
+                #normalized
+            }
+
+            let key = normalize(v);
+            #(#checks)*
+            ::core::option::Option::None
+        }
+    }
+}
+
+// Two variants must never share a name: on input the first match arm would silently win, and on
+// output both would round-trip to the same string. On input, names are compared after the
+// built-in normalizers, since `"Active"` and `"ACTIVE"` clash once matching ignores case. A
+// name repeated within one variant (such as an alias equal to its own rename) is harmless and
+// skipped when generating the match.
+// Only the directions being implemented need their names to be unique.
+fn check_collisions(
+    variants: &[KnownVariant],
+    normalizers: &[Normalizer],
+    emit: Emit,
+) -> syn::Result<()> {
+    if emit.deserialize {
+        let deserialize = variants.iter().flat_map(|v| {
+            v.accepted_names().into_iter().map(move |name| {
+                let key = normalize::apply_all(normalizers, &name.value)
+                    .unwrap_or_else(|| name.value.clone());
+                (v, name, key)
+            })
+        });
+        check_unique(deserialize, "deserialize from")?;
+    }
+
+    if emit.serialize {
+        let serialize = variants
+            .iter()
+            .filter(|v| !v.skip_serializing)
+            .map(|v| (v, &v.serialize_name, v.serialize_name.value.clone()));
+        check_unique(serialize, "serialize as")?;
+    }
+
+    Ok(())
+}
+
+// Two fields of a struct or struct variant accepting the same key would leave one of them
+// unreachable.
+fn check_field_names(fields: &[StructField]) -> syn::Result<()> {
+    let mut seen: Vec<(usize, &Name)> = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        for name in std::iter::once(&field.deserialize_name).chain(&field.aliases) {
+            if seen
+                .iter()
+                .any(|(other, seen)| *other != index && seen.value == name.value)
+            {
+                return Err(syn::Error::new(
+                    name.span,
+                    format!("{:?} is already accepted by another field", name.value),
+                ));
+            }
+            seen.push((index, name));
+        }
+    }
+    Ok(())
+}
+
+// Two fields written under the same key would produce a duplicate key.
+fn check_serialize_names(fields: &[StructField]) -> syn::Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        if fields[..index]
+            .iter()
+            .any(|other| other.name.value == field.name.value)
+        {
+            return Err(syn::Error::new(
+                field.name.span,
+                format!("{:?} is already written by another field", field.name.value),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// A unit catch-all is written as its own name, which would read back as a known variant
+// written the same way.
+fn check_unit_catch_all(variants: &[KnownVariant], path: &Path, name: &Name) -> syn::Result<()> {
+    let Some(known) = variants
+        .iter()
+        .find(|v| !v.skip_serializing && v.serialize_name.value == name.value)
+    else {
+        return Ok(());
+    };
+    let catch_all = &path.segments.last().expect("variant path").ident;
+    let mut err = syn::Error::new(
+        known.serialize_name.span,
+        format!(
+            "variants `{}` and `{}` both serialize as {:?}",
+            known.ident, catch_all, name.value
+        ),
+    );
+    err.combine(syn::Error::new(
+        name.span,
+        format!(
+            "the unit catch-all `{}` is written as {:?}",
+            catch_all, name.value
+        ),
+    ));
+    Err(err)
+}
+
+fn check_unique<'a>(
+    names: impl Iterator<Item = (&'a KnownVariant, &'a Name, String)>,
+    direction: &str,
+) -> syn::Result<()> {
+    let mut seen = std::collections::HashMap::<String, (&KnownVariant, &Name)>::new();
+
+    for (variant, name, key) in names {
+        match seen.get(&key) {
+            Some((first, _)) if first.ident == variant.ident => {}
+            Some((first, first_name)) => {
+                let message = if first_name.value == name.value {
+                    format!(
+                        "variants `{}` and `{}` both {} {:?}",
+                        first.ident, variant.ident, direction, name.value
+                    )
+                } else {
+                    format!(
+                        "variants `{}` ({:?}) and `{}` ({:?}) both {} {:?} once normalized",
+                        first.ident, first_name.value, variant.ident, name.value, direction, key
+                    )
+                };
+                let mut err = syn::Error::new(name.span, message);
+                err.combine(syn::Error::new(
+                    first_name.span,
+                    format!("`{}` first uses {:?} here", first.ident, first_name.value),
+                ));
+                return Err(err);
+            }
+            None => {
+                seen.insert(key, (variant, name));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_catch_all_attr(a: &Attribute) -> bool {
+    a.path().is_ident("catch_all")
+}
+
+// A variant is the catch-all when marked `#[catch_all]`, or `#[serde(other)]` like serde's own
+// fallback.
+fn is_catch_all_variant(v: &Variant) -> syn::Result<bool> {
+    Ok(v.attrs.iter().any(is_catch_all_attr) || extract_other(&v.attrs)?)
+}
+
+/// How the catch-all payload is produced from the unknown string.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Payload {
+    /// Built from an owned `String` through `From<String>`.
+    Owned,
+    /// A smart string that is cheaper to build from `&str` than from an intermediate `String`.
+    OwnedFromStr,
+    /// `&'a str`, which can only borrow from the input and rejects transient strings.
+    BorrowedStr,
+    /// `Cow<'a, str>` marked `#[serde(borrow)]`, which borrows when the input allows it and
+    /// falls back to an owned string otherwise (e.g. for escaped JSON strings).
+    Cow,
+    /// Nothing: a unit catch-all drops the unknown value.
+    Unit,
+}
+
+impl Payload {
+    fn classify(ty: &syn::Type, borrow: bool) -> Self {
+        if is_borrowed_str_type(ty) {
+            // Like serde, `&str` always borrows without needing `#[serde(borrow)]`
+            Payload::BorrowedStr
+        } else if borrow && is_cow_str_type(ty) {
+            Payload::Cow
+        } else if is_smart_string_type(ty) {
+            Payload::OwnedFromStr
+        } else {
+            Payload::Owned
+        }
+    }
+}
+
+fn is_borrowed_str_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) => {
+            r.lifetime.is_some() && r.mutability.is_none() && is_str_type(&r.elem)
+        }
+        _ => false,
+    }
+}
+
+fn is_cow_str_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(tp) = ty else {
+        return false;
+    };
+    let Some(last) = tp.path.segments.last() else {
+        return false;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+        return false;
+    };
+    last.ident == "Cow"
+        && matches!(
+            args.args.iter().collect::<Vec<_>>().as_slice(),
+            [syn::GenericArgument::Lifetime(_), syn::GenericArgument::Type(inner)] if is_str_type(inner)
+        )
+}
+
+fn is_str_type(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(tp) if tp.qself.is_none() && tp.path.is_ident("str"))
+}
+
+// The lifetimes a borrowed payload (`&'a str` or `Cow<'a, str>`) borrows for.
+fn borrowed_lifetimes(ty: &syn::Type) -> Vec<syn::Lifetime> {
+    match ty {
+        syn::Type::Reference(r) => r.lifetime.iter().cloned().collect(),
+        syn::Type::Path(tp) => tp
+            .path
+            .segments
+            .iter()
+            .flat_map(|segment| match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => args
+                    .args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        syn::GenericArgument::Lifetime(lifetime) => Some(lifetime.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Primitive integers, which make a catch-all collect unknown discriminants instead of names.
+fn is_integer_type(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(tp) if tp.qself.is_none() && tp.path.get_ident().is_some_and(|ident| {
+        matches!(
+            ident.to_string().as_str(),
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+        )
+    }))
+}
+
+fn is_string_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(tp) => {
+            let last = tp.path.segments.last().map(|s| s.ident.to_string());
+            matches!(last.as_deref(), Some("String"))
+        }
+        _ => false,
+    }
+}
+
+// Owned string types with a `From<&str>` impl that avoids going through a `String` first.
+fn is_smart_string_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(tp) = ty else {
+        return false;
+    };
+    let Some(last) = tp.path.segments.last() else {
+        return false;
+    };
+
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args) => {
+            matches!(last.ident.to_string().as_str(), "Box" | "Arc" | "Rc")
+                && matches!(
+                    args.args.iter().collect::<Vec<_>>().as_slice(),
+                    [syn::GenericArgument::Type(inner)] if is_str_type(inner)
+                )
+        }
+        syn::PathArguments::None => {
+            (cfg!(feature = "smol_str") && last.ident == "SmolStr")
+                || (cfg!(feature = "compact_str") && last.ident == "CompactString")
+        }
+        syn::PathArguments::Parenthesized(_) => false,
+    }
+}
+
+fn variant_path(enum_ident: &syn::Ident, v: &Variant) -> Path {
+    let variant_ident = &v.ident;
+    syn::parse_quote! { #enum_ident :: #variant_ident }
+}
+
+struct RenameAll {
+    serialize: RenameRule,
+    deserialize: RenameRule,
+}
+
+/// The `rename` of a variant or field, which may name each direction separately.
+#[derive(Default)]
+struct Rename {
+    serialize: Option<Name>,
+    deserialize: Option<Name>,
+}
+
+// Extract the container-level `rename_all`, which is either a single rule applied to both
+// directions or `rename_all(serialize = "...", deserialize = "...")`.
+fn extract_rename_all(attrs: &[Attribute]) -> syn::Result<RenameAll> {
+    let mut serialize = None;
+    let mut deserialize = None;
+
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        let Meta::List(list) = &attr.meta else {
+            continue;
+        };
+        let nested = list.parse_args_with(
+            syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+        )?;
+
+        for meta in nested {
+            if !meta.path().is_ident("rename_all") {
+                continue;
+            }
+
+            match &meta {
+                Meta::NameValue(MetaNameValue { path, value, .. }) => {
+                    let rule = parse_rename_rule(value)?;
+                    set_once(&mut serialize, rule, path, "rename_all")?;
+                    set_once(&mut deserialize, rule, path, "rename_all")?;
+                }
+                Meta::List(list) => {
+                    let directions = list.parse_args_with(
+                        syn::punctuated::Punctuated::<MetaNameValue, syn::Token![,]>::parse_terminated,
+                    )?;
+                    for MetaNameValue { path, value, .. } in &directions {
+                        if path.is_ident("serialize") {
+                            let rule = parse_rename_rule(value)?;
+                            set_once(&mut serialize, rule, path, "rename_all")?;
+                        } else if path.is_ident("deserialize") {
+                            let rule = parse_rename_rule(value)?;
+                            set_once(&mut deserialize, rule, path, "rename_all")?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                path,
+                                "expected `serialize` or `deserialize` in `rename_all(...)`",
+                            ));
+                        }
+                    }
+                }
+                Meta::Path(_) => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        "expected `rename_all = \"...\"`",
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(RenameAll {
+        serialize: serialize.unwrap_or(RenameRule::None),
+        deserialize: deserialize.unwrap_or(RenameRule::None),
+    })
+}
+
+// Like serde, a key given twice for the same direction is an error rather than the last one
+// silently winning.
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &syn::Path, name: &str) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(syn::Error::new_spanned(
+            key,
+            format!("duplicate serde attribute `{}`", name),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn parse_rename_rule(value: &Expr) -> syn::Result<RenameRule> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => RenameRule::from_str(&s.value())
+            .map_err(|err| syn::Error::new_spanned(s, err.to_string())),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            "expected a string literal such as \"snake_case\"",
+        )),
+    }
+}
+
+// Whether the catch-all variant opts into zero-copy deserialization with `#[serde(borrow)]`.
+fn extract_borrow(attrs: &[Attribute]) -> syn::Result<bool> {
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        if let Meta::List(list) = &attr.meta {
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            )?;
+            if nested.iter().any(|meta| meta.path().is_ident("borrow")) {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+// Whether a variant is marked `#[serde(other)]`.
+fn extract_other(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut other = false;
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        if let Meta::List(list) = &attr.meta {
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            )?;
+            for meta in nested {
+                if !meta.path().is_ident("other") {
+                    continue;
+                }
+                if !matches!(meta, Meta::Path(_)) {
+                    return Err(syn::Error::new_spanned(&meta, "expected a bare `other`"));
+                }
+                other = true;
+            }
+        }
+    }
+
+    Ok(other)
+}
+
+// Whether a variant is `#[serde(skip_serializing)]` and `#[serde(skip_deserializing)]`, with
+// `#[serde(skip)]` meaning both.
+fn extract_skip(attrs: &[Attribute]) -> syn::Result<(bool, bool)> {
+    let mut skip = (None, None);
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        if let Meta::List(list) = &attr.meta {
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            )?;
+            for meta in nested {
+                let path = meta.path();
+                let is_skip = ["skip", "skip_serializing", "skip_deserializing"]
+                    .iter()
+                    .any(|key| path.is_ident(key));
+                if is_skip && !matches!(meta, Meta::Path(_)) {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        format!("expected a bare `{}`", quote!(#path)),
+                    ));
+                }
+                if path.is_ident("skip") || path.is_ident("skip_serializing") {
+                    set_once(&mut skip.0, (), path, "skip_serializing")?;
+                }
+                if path.is_ident("skip") || path.is_ident("skip_deserializing") {
+                    set_once(&mut skip.1, (), path, "skip_deserializing")?;
+                }
+            }
+        }
+    }
+
+    Ok((skip.0.is_some(), skip.1.is_some()))
+}
+
+// Every key of every `#[serde(...)]` attribute must be one the macro applies in this position,
+// so that nothing is silently dropped along with the attribute.
+fn check_serde_keys(attrs: &[Attribute], supported: &[&str]) -> syn::Result<()> {
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        let Meta::List(list) = &attr.meta else {
+            return Err(syn::Error::new_spanned(attr, "expected `#[serde(...)]`"));
+        };
+        let nested = list.parse_args_with(
+            syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+        )?;
+        for meta in nested {
+            let path = meta.path();
+            if supported.iter().any(|key| path.is_ident(key)) {
+                continue;
+            }
+
+            let key = quote!(#path).to_string().replace(' ', "");
+            let message = match supported {
+                [] => format!(
+                    "unsupported serde attribute `{}`, no serde attributes are supported here",
+                    key
+                ),
+                [only] => format!("unsupported serde attribute `{}`, expected `{}`", key, only),
+                [init @ .., last] => format!(
+                    "unsupported serde attribute `{}`, expected {}{} or `{}`",
+                    key,
+                    if init.len() > 1 { "one of " } else { "" },
+                    init.iter()
+                        .map(|key| format!("`{}`", key))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    last
+                ),
+            };
+            return Err(syn::Error::new_spanned(path, message));
+        }
+    }
+
+    Ok(())
+}
+
+// Extract serde rename/alias using syn v2 API.
+// Returns (rename, aliases_vec)
+fn extract_serde_names(attrs: &[Attribute]) -> syn::Result<(Rename, Vec<Name>)> {
+    let mut rename = Rename::default();
+    let mut aliases: Vec<Name> = Vec::new();
+
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        // Parse the attribute using syn v2 API
+        if let Meta::List(list) = &attr.meta {
+            // Parse as a list of nested meta items
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            )?;
+
+            for meta in nested {
+                match meta {
+                    Meta::NameValue(MetaNameValue {
+                        path,
+                        value:
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(s), ..
+                            }),
+                        ..
+                    }) if path.is_ident("rename") => {
+                        set_once(&mut rename.serialize, Name::from_lit(&s), &path, "rename")?;
+                        set_once(&mut rename.deserialize, Name::from_lit(&s), &path, "rename")?;
+                    }
+                    Meta::List(list) if list.path.is_ident("rename") => {
+                        let directions = list.parse_args_with(
+                            syn::punctuated::Punctuated::<MetaNameValue, syn::Token![,]>::parse_terminated,
+                        )?;
+                        for MetaNameValue { path, value, .. } in &directions {
+                            let Expr::Lit(ExprLit {
+                                lit: Lit::Str(s), ..
+                            }) = value
+                            else {
+                                return Err(syn::Error::new_spanned(
+                                    value,
+                                    "expected a string literal",
+                                ));
+                            };
+                            if path.is_ident("serialize") {
+                                set_once(&mut rename.serialize, Name::from_lit(s), path, "rename")?;
+                            } else if path.is_ident("deserialize") {
+                                set_once(
+                                    &mut rename.deserialize,
+                                    Name::from_lit(s),
+                                    path,
+                                    "rename",
+                                )?;
+                            } else {
+                                return Err(syn::Error::new_spanned(
+                                    path,
+                                    "expected `serialize` or `deserialize` in `rename(...)`",
+                                ));
+                            }
+                        }
+                    }
+                    Meta::NameValue(MetaNameValue {
+                        path,
+                        value:
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(s), ..
+                            }),
+                        ..
+                    }) if path.is_ident("alias") => {
+                        aliases.push(Name::from_lit(&s));
+                    }
+                    meta if meta.path().is_ident("rename") => {
+                        return Err(syn::Error::new_spanned(
+                            &meta,
+                            "expected `rename = \"...\"` or `rename(serialize = \"...\", deserialize = \"...\")`",
+                        ));
+                    }
+                    meta if meta.path().is_ident("alias") => {
+                        return Err(syn::Error::new_spanned(
+                            &meta,
+                            "expected `alias = \"...\"` with a string literal",
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok((rename, aliases))
+}
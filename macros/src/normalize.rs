@@ -0,0 +1,128 @@
+//! Matching normalizers for `#[serde_catch_all(normalize(...))]`.
+//!
+//! The built-in steps are applied here to the known names at compile time and to the input at
+//! runtime through `serde_catch_all::normalize`, so both implementations must agree.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(Clone)]
+pub enum Normalizer {
+    AsciiCase,
+    UnicodeCase,
+    Trim,
+    Separators,
+    Nfc,
+    Nfkc,
+    /// A user-provided `fn(Cow<str>) -> Cow<str>`, which can only run at runtime.
+    Custom(syn::Path),
+}
+
+static BUILTIN: &[&str] = &[
+    "ascii_case",
+    "unicode_case",
+    "trim",
+    "separators",
+    "nfc",
+    "nfkc",
+];
+
+impl Normalizer {
+    pub fn from_path(path: &syn::Path) -> syn::Result<Self> {
+        let found = path.get_ident().and_then(|ident| {
+            Some(match ident.to_string().as_str() {
+                "ascii_case" => Normalizer::AsciiCase,
+                "unicode_case" => Normalizer::UnicodeCase,
+                "trim" => Normalizer::Trim,
+                "separators" => Normalizer::Separators,
+                "nfc" => Normalizer::Nfc,
+                "nfkc" => Normalizer::Nfkc,
+                _ => return None,
+            })
+        });
+
+        match found {
+            Some(Normalizer::Nfc | Normalizer::Nfkc) if !cfg!(feature = "unicode") => {
+                Err(syn::Error::new_spanned(
+                    path,
+                    "Unicode normalization requires the `unicode` feature of serde_catch_all",
+                ))
+            }
+            Some(normalizer) => Ok(normalizer),
+            None => Err(syn::Error::new_spanned(
+                path,
+                format!(
+                    "unknown normalizer, expected one of {} or `with = path::to::fn`",
+                    BUILTIN.join(", ")
+                ),
+            )),
+        }
+    }
+
+    /// Apply the step to a known name, or `None` for custom steps.
+    pub fn apply(&self, s: &str) -> Option<String> {
+        Some(match self {
+            Normalizer::AsciiCase => s.to_ascii_lowercase(),
+            Normalizer::UnicodeCase => s.to_lowercase(),
+            Normalizer::Trim => s.trim().to_owned(),
+            Normalizer::Separators => s.replace(['-', ' '], "_"),
+            Normalizer::Nfc => nfc(s),
+            Normalizer::Nfkc => nfkc(s),
+            Normalizer::Custom(_) => return None,
+        })
+    }
+
+    /// Path to the runtime step with the `fn(Cow<str>) -> Cow<str>` signature.
+    pub fn runtime_fn(&self) -> TokenStream {
+        match self {
+            Normalizer::AsciiCase => quote! { ::serde_catch_all::normalize::ascii_case },
+            Normalizer::UnicodeCase => quote! { ::serde_catch_all::normalize::unicode_case },
+            Normalizer::Trim => quote! { ::serde_catch_all::normalize::trim },
+            Normalizer::Separators => quote! { ::serde_catch_all::normalize::separators },
+            Normalizer::Nfc => quote! { ::serde_catch_all::normalize::nfc },
+            Normalizer::Nfkc => quote! { ::serde_catch_all::normalize::nfkc },
+            Normalizer::Custom(path) => quote! { #path },
+        }
+    }
+}
+
+/// Apply every step to a known name, or `None` when one of them only exists at runtime.
+pub fn apply_all(normalizers: &[Normalizer], s: &str) -> Option<String> {
+    normalizers
+        .iter()
+        .try_fold(s.to_owned(), |acc, normalizer| normalizer.apply(&acc))
+}
+
+/// Runtime expression normalizing the `&str` bound to `v` into a `Cow<str>`.
+pub fn runtime_expr(normalizers: &[Normalizer]) -> TokenStream {
+    normalizers.iter().fold(
+        quote! { ::serde_catch_all::__private::Cow::Borrowed(v) },
+        |acc, normalizer| {
+            let step = normalizer.runtime_fn();
+            quote! { #step(#acc) }
+        },
+    )
+}
+
+#[cfg(feature = "unicode")]
+fn nfc(s: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    s.nfc().collect()
+}
+
+#[cfg(feature = "unicode")]
+fn nfkc(s: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    s.nfkc().collect()
+}
+
+// Rejected by `from_path` without the feature, so never reached.
+#[cfg(not(feature = "unicode"))]
+fn nfc(s: &str) -> String {
+    s.to_owned()
+}
+
+#[cfg(not(feature = "unicode"))]
+fn nfkc(s: &str) -> String {
+    s.to_owned()
+}
@@ -0,0 +1,223 @@
+//! Options passed to the attribute itself, `#[serde_catch_all(...)]`, or to the derives as
+//! `#[catch_all(...)]`.
+
+use proc_macro2::TokenStream;
+use syn::{Expr, ExprLit, ExprPath, Lit, Meta, MetaNameValue};
+
+use crate::normalize::Normalizer;
+
+#[derive(Default)]
+pub struct ContainerOptions {
+    /// Steps applied, in order, before matching an input against the known names.
+    pub normalizers: Vec<Normalizer>,
+    /// How known variants are written out. Anything but `Name` switches on integer input.
+    pub serialize_as: SerializeAs,
+    /// The field naming the variant of an internally or adjacently tagged enum.
+    pub tag: Option<syn::LitStr>,
+    /// The field holding the payload of an adjacently tagged enum.
+    pub content: Option<syn::LitStr>,
+    /// Whether unknown values are rejected instead of landing in the catch-all.
+    pub strict: bool,
+    /// A `fn(&'static str, &str)` told about unknown names instead of the global observer.
+    pub observe: Option<syn::Path>,
+    /// `serialize_only` or `deserialize_only`, when just one direction is implemented.
+    pub only: Option<syn::Ident>,
+    /// Whether formats that are not human-readable get serde's enum form, for formats like
+    /// bincode that cannot tell what kind of value comes next on their own.
+    pub non_self_describing: bool,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum SerializeAs {
+    /// The variant name, as a string.
+    #[default]
+    Name,
+    /// The discriminant, as an integer.
+    Number,
+    /// The discriminant for formats that are not human-readable, the name otherwise.
+    NumberIfBinary,
+}
+
+impl ContainerOptions {
+    pub fn parse(args: TokenStream) -> syn::Result<Self> {
+        let mut options = ContainerOptions::default();
+
+        let nested = syn::parse::Parser::parse2(
+            syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            args,
+        )?;
+
+        for meta in nested {
+            match &meta {
+                Meta::Path(path) if path.is_ident("case_insensitive") => {
+                    options.normalizers.push(Normalizer::UnicodeCase);
+                }
+                Meta::Path(path) if path.is_ident("strict") => {
+                    options.strict = true;
+                }
+                Meta::Path(path) if path.is_ident("non_self_describing") => {
+                    options.non_self_describing = true;
+                }
+                Meta::Path(path) if is_direction(path) => {
+                    options.set_only(path)?;
+                }
+                Meta::List(list) if list.path.is_ident("normalize") => {
+                    let steps = list.parse_args_with(
+                        syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+                    )?;
+                    for step in steps {
+                        options.normalizers.push(parse_normalizer(&step)?);
+                    }
+                }
+                Meta::NameValue(MetaNameValue { path, value, .. })
+                    if path.is_ident("serialize_as") =>
+                {
+                    options.serialize_as = parse_serialize_as(value)?;
+                }
+                Meta::NameValue(MetaNameValue {
+                    path,
+                    value:
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(tag), ..
+                        }),
+                    ..
+                }) if path.is_ident("tag") => {
+                    options.tag = Some(tag.clone());
+                }
+                Meta::NameValue(MetaNameValue {
+                    path,
+                    value:
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(content),
+                            ..
+                        }),
+                    ..
+                }) if path.is_ident("content") => {
+                    options.content = Some(content.clone());
+                }
+                Meta::NameValue(MetaNameValue {
+                    path,
+                    value: Expr::Path(ExprPath { path: observer, .. }),
+                    ..
+                }) if path.is_ident("observe") => {
+                    options.observe = Some(observer.clone());
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        "unknown serde_catch_all option, expected `case_insensitive`, `normalize(...)`, `serialize_as = \"...\"`, `tag = \"...\"`, `content = \"...\"`, `strict`, `observe = path::to::fn`, `non_self_describing`, `serialize_only` or `deserialize_only`",
+                    ));
+                }
+            }
+        }
+
+        if let Some(content) = &options.content {
+            match &options.tag {
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        content,
+                        "`content` needs a `tag` to go with it",
+                    ));
+                }
+                Some(tag) if tag.value() == content.value() => {
+                    return Err(syn::Error::new_spanned(
+                        content,
+                        "`tag` and `content` must name different fields",
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Parses the options of a struct, which can only pick the direction to implement.
+    pub fn parse_struct(args: TokenStream) -> syn::Result<Self> {
+        let mut options = ContainerOptions::default();
+
+        let nested = syn::parse::Parser::parse2(
+            syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+            args,
+        )?;
+
+        for meta in nested {
+            match &meta {
+                Meta::Path(path) if is_direction(path) => {
+                    options.set_only(path)?;
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        "unknown serde_catch_all option for a struct, expected `serialize_only` or `deserialize_only`",
+                    ));
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn set_only(&mut self, path: &syn::Path) -> syn::Result<()> {
+        let only = path.require_ident()?;
+        match &self.only {
+            Some(other) if other != only => Err(syn::Error::new_spanned(
+                only,
+                "`serialize_only` and `deserialize_only` cannot be combined, leave both out to implement both directions",
+            )),
+            _ => {
+                self.only = Some(only.clone());
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether unknown values are rejected, either by this enum's `strict` option or by the
+    /// `strict` feature for every enum.
+    pub fn is_strict(&self) -> bool {
+        self.strict || cfg!(feature = "strict")
+    }
+
+    /// The tag field if the enum is internally tagged, i.e. its variants' fields sit next to it.
+    pub fn internal_tag(&self) -> Option<&syn::LitStr> {
+        self.tag.as_ref().filter(|_| self.content.is_none())
+    }
+}
+
+fn is_direction(path: &syn::Path) -> bool {
+    path.is_ident("serialize_only") || path.is_ident("deserialize_only")
+}
+
+fn parse_normalizer(meta: &Meta) -> syn::Result<Normalizer> {
+    match meta {
+        Meta::Path(path) => Normalizer::from_path(path),
+        Meta::NameValue(MetaNameValue {
+            path,
+            value: Expr::Path(ExprPath { path: step, .. }),
+            ..
+        }) if path.is_ident("with") => Ok(Normalizer::Custom(step.clone())),
+        _ => Err(syn::Error::new_spanned(
+            meta,
+            "expected a normalizer such as `trim`, or `with = path::to::fn`",
+        )),
+    }
+}
+
+fn parse_serialize_as(value: &Expr) -> syn::Result<SerializeAs> {
+    if let Expr::Lit(ExprLit {
+        lit: Lit::Str(s), ..
+    }) = value
+    {
+        match s.value().as_str() {
+            "name" => return Ok(SerializeAs::Name),
+            "number" => return Ok(SerializeAs::Number),
+            "number_if_binary" => return Ok(SerializeAs::NumberIfBinary),
+            _ => {}
+        }
+    }
+
+    Err(syn::Error::new_spanned(
+        value,
+        "expected `serialize_as = \"name\"`, `\"number\"` or `\"number_if_binary\"`",
+    ))
+}
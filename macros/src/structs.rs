@@ -0,0 +1,272 @@
+//! Structs with a `#[catch_all]` map field collecting every key that is not one of the other
+//! fields.
+//!
+//! Unlike `#[serde(flatten)]`, nothing is buffered: a single map visitor deserializes known
+//! fields in place and pushes anything else into the catch-all map as it goes, so the payload
+//! type alone decides which formats work. Serializing writes the known fields followed by the
+//! catch-all entries, in the map's own order.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+
+use crate::{
+    check_field_names, check_serde_keys, check_serialize_names, extract_rename_all,
+    is_catch_all_attr, is_string_type, Emit, StructField,
+};
+
+pub fn expand(
+    input: &syn::DeriveInput,
+    data: &syn::DataStruct,
+    emit: Emit,
+) -> syn::Result<TokenStream> {
+    let struct_ident = &input.ident;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let syn::Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "#[serde_catch_all] structs must have named fields",
+        ));
+    };
+
+    check_serde_keys(&input.attrs, &["rename_all"])?;
+    let rename_all = extract_rename_all(&input.attrs)?;
+    let mut fields = Vec::new();
+    let mut catch_all = None;
+    for field in &named.named {
+        if !field.attrs.iter().any(is_catch_all_attr) {
+            fields.push(StructField::analyze(field, Some(&rename_all), None)?);
+            continue;
+        }
+        if catch_all.is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only one #[catch_all] field is allowed",
+            ));
+        }
+        check_serde_keys(&field.attrs, &[])?;
+        catch_all = Some(field);
+    }
+    let catch_all = catch_all.ok_or_else(|| {
+        syn::Error::new_spanned(
+            struct_ident,
+            "you must provide exactly one #[catch_all] field holding a map, such as `extra: HashMap<String, Value>`",
+        )
+    })?;
+    let extra_ident = catch_all.ident.as_ref().expect("named field");
+    let map_ty = &catch_all.ty;
+    let (key_ty, value_ty) = map_types(map_ty)?;
+    if emit.deserialize {
+        check_field_names(&fields)?;
+    }
+    if emit.serialize {
+        check_serialize_names(&fields)?;
+    }
+
+    // Known fields and catch-all values are read from and written to the input, so each gets
+    // the matching bound, spanned on its type. The map is filled with `Default` + `Extend` and
+    // written out by iterating over a reference, which every common map type supports.
+    let mut de_where_clause = generics
+        .where_clause
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote! { where });
+    let mut ser_where_clause = de_where_clause.clone();
+    for ty in fields.iter().map(|field| &field.ty).chain([value_ty]) {
+        let span = ty.span();
+        de_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #ty: ::serde::Deserialize<'de>
+            });
+        ser_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #ty: ::serde::Serialize
+            });
+    }
+    let span = map_ty.span();
+    de_where_clause
+        .predicates
+        .push(syn::parse_quote_spanned! {span=>
+            #map_ty: ::core::default::Default + ::core::iter::Extend<(#key_ty, #value_ty)>
+        });
+    ser_where_clause
+        .predicates
+        .push(syn::parse_quote_spanned! {span=>
+            for<'__a> &'__a #map_ty: ::core::iter::IntoIterator<Item = (&'__a #key_ty, &'__a #value_ty)>
+        });
+    let span = key_ty.span();
+    if !is_string_type(key_ty) {
+        de_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #key_ty: ::core::convert::From<::serde_catch_all::__private::String>
+            });
+    }
+    ser_where_clause
+        .predicates
+        .push(syn::parse_quote_spanned! {span=>
+            #key_ty: ::serde::Serialize
+        });
+
+    let mut de_generics = generics.clone();
+    de_generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(syn::parse_quote! { 'de }));
+    let (de_impl_generics, de_ty_generics, _) = de_generics.split_for_impl();
+
+    let slots: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let tys = fields.iter().map(|field| &field.ty);
+    let key_arms = fields.iter().zip(&slots).map(|(field, slot)| {
+        let name = &field.deserialize_name;
+        let aliases = &field.aliases;
+        quote! {
+            #name #(| #aliases)* => {
+                if #slot.is_some() {
+                    return ::core::result::Result::Err(
+                        ::serde::de::Error::duplicate_field(#name),
+                    );
+                }
+                #slot = ::core::option::Option::Some(map.next_value()?);
+            }
+        }
+    });
+    let inits = fields.iter().zip(&slots).map(|(field, slot)| {
+        let ident = &field.ident;
+        let name = &field.deserialize_name;
+        quote! {
+            #ident: match #slot {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => {
+                    ::serde_catch_all::__private::missing_field(#name)?
+                }
+            },
+        }
+    });
+    let expecting = format!("struct {}", struct_ident);
+
+    let known_len = fields.len();
+    let idents = fields.iter().map(|field| &field.ident);
+    let names = fields.iter().map(|field| &field.name);
+
+    // Keep the user's struct but without problematic attributes, unless a derive left it as is
+    let cleaned_input = emit.item.then(|| {
+        let mut cleaned_input = input.clone();
+        cleaned_input
+            .attrs
+            .retain(|attr| !attr.path().is_ident("serde"));
+        if let syn::Data::Struct(data) = &mut cleaned_input.data {
+            for field in &mut data.fields {
+                field
+                    .attrs
+                    .retain(|attr| !is_catch_all_attr(attr) && !attr.path().is_ident("serde"));
+            }
+        }
+        cleaned_input
+    });
+
+    let deserialize = emit.deserialize.then(|| quote! {
+        impl #de_impl_generics ::serde::Deserialize<'de> for #struct_ident #ty_generics #de_where_clause {
+            fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                struct __Visitor #de_impl_generics #de_where_clause {
+                    marker: ::core::marker::PhantomData<#struct_ident #ty_generics>,
+                    lifetime: ::core::marker::PhantomData<&'de ()>,
+                }
+
+                impl #de_impl_generics ::serde::de::Visitor<'de> for __Visitor #de_ty_generics #de_where_clause {
+                    type Value = #struct_ident #ty_generics;
+
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_map<__A>(self, mut map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+                    where
+                        __A: ::serde::de::MapAccess<'de>,
+                    {
+                        #(let mut #slots: ::core::option::Option<#tys> = ::core::option::Option::None;)*
+                        let mut extra: #map_ty = ::core::default::Default::default();
+                        while let ::core::option::Option::Some(key) = map.next_key::<::serde_catch_all::__private::String>()? {
+                            match key.as_str() {
+                                #(#key_arms)*
+                                _ => {
+                                    let value: #value_ty = map.next_value()?;
+                                    ::core::iter::Extend::extend(
+                                        &mut extra,
+                                        ::core::iter::once((::core::convert::From::from(key), value)),
+                                    );
+                                }
+                            }
+                        }
+                        ::core::result::Result::Ok(#struct_ident {
+                            #(#inits)*
+                            #extra_ident: extra,
+                        })
+                    }
+                }
+
+                deserializer.deserialize_map(__Visitor {
+                    marker: ::core::marker::PhantomData,
+                    lifetime: ::core::marker::PhantomData,
+                })
+            }
+        }
+    });
+
+    let serialize = emit.serialize.then(|| quote! {
+        impl #impl_generics ::serde::Serialize for #struct_ident #ty_generics #ser_where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                use ::serde::ser::SerializeMap as _;
+
+                let len = #known_len + ::core::iter::Iterator::count(
+                    ::core::iter::IntoIterator::into_iter(&self.#extra_ident),
+                );
+                let mut map = serializer.serialize_map(::core::option::Option::Some(len))?;
+                #(map.serialize_entry(#names, &self.#idents)?;)*
+                for (key, value) in &self.#extra_ident {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    });
+
+    Ok(quote! {
+        #cleaned_input
+        #deserialize
+        #serialize
+    })
+}
+
+// The key and value types of a map such as `HashMap<K, V>`, `BTreeMap<K, V>` or
+// `serde_json::Map<K, V>`: its first two type arguments.
+fn map_types(ty: &syn::Type) -> syn::Result<(&syn::Type, &syn::Type)> {
+    if let syn::Type::Path(tp) = ty {
+        if let Some(syn::PathArguments::AngleBracketed(args)) =
+            tp.path.segments.last().map(|last| &last.arguments)
+        {
+            let mut types = args.args.iter().filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            });
+            if let (Some(key), Some(value)) = (types.next(), types.next()) {
+                return Ok((key, value));
+            }
+        }
+    }
+
+    Err(syn::Error::new_spanned(
+        ty,
+        "the #[catch_all] field must be a map such as `HashMap<String, V>` or `BTreeMap<String, V>`",
+    ))
+}
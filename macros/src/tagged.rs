@@ -0,0 +1,1082 @@
+//! Enums whose variants carry data: internally tagged with `#[serde_catch_all(tag = "type")]`,
+//! adjacently tagged with an added `content = "data"`, or externally tagged like serde's default
+//! `{"Variant": payload}`.
+//!
+//! An internally tagged object is read in full first, with everything but the tag buffered as
+//! `serde_catch_all::__private::Content`, and then replayed into whichever variant the tag names.
+//! Adjacently tagged content only needs buffering when it comes before the tag.
+//! Externally tagged values are either a bare variant name or a map with the name as its only
+//! key. In every case, an unknown name is kept in the catch-all together with, optionally, the
+//! rest of the value, which serializes back into the same shape.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+
+use crate::options::ContainerOptions;
+use crate::{
+    is_string_type, lookup_body, observe_unknown, observer_check, skipped_serializing_check,
+    unknown_variant_error, Emit, KnownVariant, Name, Shape, StructField,
+};
+
+pub struct TaggedEnum<'a> {
+    pub input: &'a syn::DeriveInput,
+    pub cleaned_input: Option<syn::DeriveInput>,
+    pub options: &'a ContainerOptions,
+    /// The field holding the variant name, or `None` when externally tagged.
+    pub tag: Option<&'a syn::LitStr>,
+    /// The field holding the payload when adjacently tagged.
+    pub content: Option<&'a syn::LitStr>,
+    pub known_variants: &'a [KnownVariant],
+    pub catch_all_path: &'a syn::Path,
+    pub catch_all_ty: &'a syn::Type,
+    /// The catch-all field holding the name, which is always its first one.
+    pub name_member: &'a syn::Member,
+    /// For a unit catch-all, which drops the unknown name and payload, the name it is written as.
+    pub catch_all_unit: Option<&'a Name>,
+    pub catch_all_rest: Option<&'a (syn::Member, syn::Type)>,
+    pub introspection: TokenStream,
+    pub emit: Emit,
+}
+
+pub fn expand(e: TaggedEnum) -> TokenStream {
+    let TaggedEnum {
+        input,
+        cleaned_input,
+        options,
+        tag,
+        content,
+        known_variants,
+        catch_all_path,
+        catch_all_ty,
+        name_member,
+        catch_all_unit,
+        catch_all_rest,
+        introspection,
+        emit,
+    } = e;
+
+    let enum_ident = &input.ident;
+    let enum_vis = &input.vis;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let lookup_body = lookup_body(known_variants, &options.normalizers, |index, _| {
+        quote! { #index }
+    });
+    let lookup = quote! { <#enum_ident #ty_generics>::__serde_catch_all_variant };
+    let rest_member = catch_all_rest.map(|(member, _)| member);
+    // Strict enums reject unknown names instead of building the catch-all,
+    // and the others tell the observer about them first.
+    let strict = options.is_strict();
+    let unknown = |catch_all: TokenStream, value: TokenStream, error: TokenStream| {
+        if strict {
+            let error = unknown_variant_error(known_variants, value, error);
+            quote! { return #error }
+        } else {
+            let observe = observe_unknown(options, enum_ident, value);
+            quote! {{
+                #observe
+                #catch_all
+            }}
+        }
+    };
+    // The catch-all built from an unknown name, and when serializing, the pattern and the name
+    // as a `&str`. A unit catch-all drops the name and is written as its own.
+    let new_catch_all = |name: TokenStream, rest_init: Option<TokenStream>| match catch_all_unit {
+        Some(_) => quote! { #catch_all_path },
+        None => quote! {
+            #catch_all_path {
+                #name_member: ::core::convert::From::from(#name),
+                #rest_init
+            }
+        },
+    };
+    let (catch_all_pat, catch_all_name) = match catch_all_unit {
+        Some(name) => (quote! { #catch_all_path }, quote! { #name }),
+        None => (
+            quote! { #catch_all_path { #name_member: name, .. } },
+            quote! { ::core::convert::AsRef::<str>::as_ref(name) },
+        ),
+    };
+    let observer_check = observer_check(options);
+    let skipped_serializing_check = skipped_serializing_check(enum_ident, known_variants);
+    // An externally tagged payload in an `Option` is `None` for a bare unknown name, so that
+    // the two forms can be told apart when serializing
+    let bare_payload = match tag {
+        Some(_) => None,
+        None => catch_all_rest.and_then(|(_, ty)| option_inner(ty)),
+    };
+    let raw = catch_all_rest.and_then(|(_, ty)| raw_payload(bare_payload.unwrap_or(ty)));
+
+    // A payload that is unit is left out when serializing, which for raw JSON means `null`
+    let rest_is_unit = match raw {
+        Some(_) => quote! { ::serde_catch_all::__private::RawPayload::is_null(rest) },
+        None => quote! { ::serde_catch_all::__private::is_unit(rest) },
+    };
+
+    // Every field is deserialized from and serialized to the input, so each gets the matching
+    // bound, spanned on its type. The name is built from and viewed as a string, like the
+    // payload of a plain catch-all.
+    let mut base_where_clause = generics
+        .where_clause
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote! { where });
+    let mut de_where_clause = base_where_clause.clone();
+    if catch_all_unit.is_none() && !is_string_type(catch_all_ty) {
+        let span = catch_all_ty.span();
+        de_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #catch_all_ty: ::core::convert::From<::serde_catch_all::__private::String>
+            });
+        base_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #catch_all_ty: ::core::convert::AsRef<str>
+            });
+    }
+    let mut ser_where_clause = base_where_clause.clone();
+    let field_types = known_variants
+        .iter()
+        .flat_map(|v| match &v.shape {
+            Shape::Unit => Vec::new(),
+            Shape::Newtype(ty) => vec![ty],
+            Shape::Tuple(tys) => tys.iter().collect(),
+            Shape::Struct(fields) => fields.iter().map(|field| &field.ty).collect(),
+        })
+        .chain(catch_all_rest.map(|(_, ty)| bare_payload.unwrap_or(ty)));
+    for ty in field_types {
+        let span = ty.span();
+        de_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #ty: ::serde::Deserialize<'de>
+            });
+        ser_where_clause
+            .predicates
+            .push(syn::parse_quote_spanned! {span=>
+                #ty: ::serde::Serialize
+            });
+    }
+
+    // A borrowed raw payload needs `'de` to outlive its lifetime
+    let mut de_lifetime: syn::LifetimeParam = syn::parse_quote! { 'de };
+    if let Some(RawPayload::Borrowed(lifetime)) = &raw {
+        de_lifetime.bounds.push(lifetime.clone());
+    }
+    let mut de_generics = generics.clone();
+    de_generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(de_lifetime));
+    let (de_impl_generics, de_ty_generics, _) = de_generics.split_for_impl();
+
+    // Items next to the impls, for the variant dispatch of adjacently tagged enums
+    let mut support_items = TokenStream::new();
+
+    let (deserialize_body, serialize_body) = match (tag, content) {
+        (Some(tag), None) => {
+            let rest_content = from_content(
+                quote! { ::serde_catch_all::__private::Content::Map(rest) },
+                TokenStream::new(),
+                quote! { __D::Error },
+            );
+
+            let de_arms = known_variants.iter().enumerate().map(|(index, v)| {
+                let path = &v.path;
+                let body = match &v.shape {
+                    Shape::Unit => quote! { #path },
+                    Shape::Newtype(_) => quote! { #path(#rest_content) },
+                    Shape::Struct(fields) => {
+                        struct_from_entries(path, fields, None, quote! { __D::Error })
+                    }
+                    Shape::Tuple(_) => unreachable!("rejected for internally tagged enums"),
+                };
+                quote! { ::core::option::Option::Some(#index) => #body, }
+            });
+            let catch_all_rest_init = rest_member.map(|member| quote! { #member: #rest_content, });
+            let internal_unknown = unknown(
+                new_catch_all(quote! { tag }, catch_all_rest_init),
+                quote! { &tag },
+                quote! { __D::Error },
+            );
+
+            let ser_arms = known_variants.iter().map(|v| {
+                let path = &v.path;
+                let name = &v.serialize_name;
+                match &v.shape {
+                    Shape::Unit => quote! {
+                        #path => {
+                            let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                            map.serialize_entry(#tag, #name)?;
+                            map.end()
+                        }
+                    },
+                    Shape::Newtype(_) => quote! {
+                        #path(inner) => {
+                            let mut map = serializer.serialize_map(::core::option::Option::None)?;
+                            map.serialize_entry(#tag, #name)?;
+                            ::serde::Serialize::serialize(
+                                inner,
+                                ::serde_catch_all::__private::FlatMapSerializer(&mut map),
+                            )?;
+                            map.end()
+                        }
+                    },
+                    Shape::Struct(fields) => {
+                        let len = fields.len() + 1;
+                        let idents = fields.iter().map(|field| &field.ident);
+                        let slots = slots(fields.len());
+                        let names = fields.iter().map(|field| &field.name);
+                        quote! {
+                            #path { #(#idents: #slots),* } => {
+                                let mut map = serializer.serialize_map(::core::option::Option::Some(#len))?;
+                                map.serialize_entry(#tag, #name)?;
+                                #(map.serialize_entry(#names, #slots)?;)*
+                                map.end()
+                            }
+                        }
+                    }
+                    Shape::Tuple(_) => unreachable!("rejected for internally tagged enums"),
+                }
+            });
+            let catch_all_ser = match rest_member {
+                Some(member) => quote! {
+                    #catch_all_path { #name_member: name, #member: rest } => {
+                        let mut map = serializer.serialize_map(::core::option::Option::None)?;
+                        map.serialize_entry(#tag, ::core::convert::AsRef::<str>::as_ref(name))?;
+                        ::serde::Serialize::serialize(
+                            rest,
+                            ::serde_catch_all::__private::FlatMapSerializer(&mut map),
+                        )?;
+                        map.end()
+                    }
+                },
+                None => quote! {
+                    #catch_all_pat => {
+                        let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                        map.serialize_entry(#tag, #catch_all_name)?;
+                        map.end()
+                    }
+                },
+            };
+
+            (
+                quote! {
+                    let (tag, rest) = ::serde_catch_all::__private::take_tag(deserializer, #tag)?;
+                    ::core::result::Result::Ok(match #lookup(&tag) {
+                        #(#de_arms)*
+                        _ => #internal_unknown,
+                    })
+                },
+                quote! {
+                    match self {
+                        #(#ser_arms)*
+                        #catch_all_ser
+                    }
+                },
+            )
+        }
+        (Some(tag), Some(content)) => {
+            // The content is read by `from_content` with whatever deserializer it comes from:
+            // the map itself when the tag came first, or the buffered `Content` otherwise
+            let content_arms = known_variants.iter().enumerate().map(|(index, v)| {
+                let path = &v.path;
+                let body = match &v.shape {
+                    Shape::Unit => quote! {{
+                        <() as ::serde::Deserialize>::deserialize(content)?;
+                        #path
+                    }},
+                    Shape::Newtype(_) => {
+                        quote! { #path(::serde::Deserialize::deserialize(content)?) }
+                    }
+                    Shape::Tuple(tys) => {
+                        let slots = slots(tys.len());
+                        quote! {{
+                            let (#(#slots,)*): (#(#tys,)*) = ::serde::Deserialize::deserialize(content)?;
+                            #path(#(#slots),*)
+                        }}
+                    }
+                    Shape::Struct(fields) => {
+                        let build = struct_from_entries(path, fields, None, quote! { __D::Error });
+                        quote! {{
+                            let rest = ::serde_catch_all::__private::entries(content)?;
+                            #build
+                        }}
+                    }
+                };
+                quote! { ::core::option::Option::Some(#index) => #body, }
+            });
+            let content_unknown = match rest_member {
+                Some(member) => new_catch_all(
+                    quote! { tag },
+                    Some(quote! { #member: ::serde::Deserialize::deserialize(content)?, }),
+                ),
+                None => {
+                    let catch_all = new_catch_all(quote! { tag }, None);
+                    quote! {{
+                        <::serde::de::IgnoredAny as ::serde::Deserialize>::deserialize(content)?;
+                        #catch_all
+                    }}
+                }
+            };
+            let content_unknown = unknown(content_unknown, quote! { &tag }, quote! { __D::Error });
+
+            let ser_arms = known_variants.iter().map(|v| {
+                let path = &v.path;
+                let name = &v.serialize_name;
+                let Some((pattern, payload)) = payload(v) else {
+                    return quote! {
+                        #path => {
+                            let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                            map.serialize_entry(#tag, #name)?;
+                            map.end()
+                        }
+                    };
+                };
+                quote! {
+                    #pattern => {
+                        let mut map = serializer.serialize_map(::core::option::Option::Some(2))?;
+                        map.serialize_entry(#tag, #name)?;
+                        map.serialize_entry(#content, #payload)?;
+                        map.end()
+                    }
+                }
+            });
+            // Content that comes before the tag is kept as raw JSON when the payload is raw, so
+            // that it can still be forwarded untouched
+            let buffer = match &raw {
+                None => quote! { ::serde_catch_all::__private::Content },
+                Some(RawPayload::Boxed) => {
+                    let (_, ty) = catch_all_rest.expect("raw payloads are catch-all fields");
+                    quote! { #ty }
+                }
+                Some(RawPayload::Borrowed(_)) => {
+                    quote! { &'de ::serde_catch_all::__private::serde_json::value::RawValue }
+                }
+            };
+
+            let raw_catch_all = matches!(raw, Some(RawPayload::Boxed)).then(|| {
+                let (member, ty) = catch_all_rest.expect("raw payloads are catch-all fields");
+                let observe_raw = observe_unknown(options, enum_ident, quote! { &tag });
+                quote! {
+                    impl #de_impl_generics ::serde_catch_all::__private::RawCatchAll<'de> for #enum_ident #ty_generics #de_where_clause {
+                        fn keeps_raw(tag: &str) -> bool {
+                            !#strict && #lookup(tag).is_none()
+                        }
+
+                        fn catch_all(tag: ::serde_catch_all::__private::String, raw: #ty) -> Self {
+                            #observe_raw
+                            #catch_all_path {
+                                #name_member: ::core::convert::From::from(tag),
+                                #member: raw,
+                            }
+                        }
+                    }
+                }
+            });
+
+            support_items = quote! {
+                #raw_catch_all
+
+                impl #de_impl_generics ::serde_catch_all::__private::AdjacentlyTagged<'de> for #enum_ident #ty_generics #de_where_clause {
+                    fn from_content<__D>(tag: ::serde_catch_all::__private::String, content: __D) -> ::core::result::Result<Self, __D::Error>
+                    where
+                        __D: ::serde::Deserializer<'de>,
+                    {
+                        ::core::result::Result::Ok(match #lookup(&tag) {
+                            #(#content_arms)*
+                            _ => #content_unknown,
+                        })
+                    }
+                }
+            };
+
+            // Unknown content that was missing, or is unit anyway, is left out again
+            let catch_all_ser = match rest_member {
+                Some(member) => quote! {
+                    #catch_all_path { #name_member: name, #member: rest } => {
+                        let name = ::core::convert::AsRef::<str>::as_ref(name);
+                        if #rest_is_unit {
+                            let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                            map.serialize_entry(#tag, name)?;
+                            map.end()
+                        } else {
+                            let mut map = serializer.serialize_map(::core::option::Option::Some(2))?;
+                            map.serialize_entry(#tag, name)?;
+                            map.serialize_entry(#content, rest)?;
+                            map.end()
+                        }
+                    }
+                },
+                None => quote! {
+                    #catch_all_pat => {
+                        let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                        map.serialize_entry(#tag, #catch_all_name)?;
+                        map.end()
+                    }
+                },
+            };
+
+            (
+                quote! {
+                    ::serde_catch_all::__private::take_adjacent::<_, #buffer, _>(deserializer, #tag, #content)
+                },
+                quote! {
+                    match self {
+                        #(#ser_arms)*
+                        #catch_all_ser
+                    }
+                },
+            )
+        }
+        (None, _) => {
+            // A bare name can only be a unit variant, or an unknown one whose payload is then
+            // deserialized from `()`
+            let bare_arms = known_variants.iter().enumerate().filter_map(|(index, v)| {
+                let path = &v.path;
+                matches!(v.shape, Shape::Unit)
+                    .then(|| quote! { ::core::option::Option::Some(#index) => #path, })
+            });
+            let bare_data_arm = known_variants
+                .iter()
+                .any(|v| !matches!(v.shape, Shape::Unit))
+                .then(|| {
+                    quote! {
+                        ::core::option::Option::Some(_) => {
+                            return ::core::result::Result::Err(__E::invalid_type(
+                                ::serde::de::Unexpected::UnitVariant,
+                                &self,
+                            ));
+                        }
+                    }
+                });
+            let bare_rest_init = rest_member.map(|member| {
+                let unit = match (bare_payload, &raw) {
+                    (Some(_), _) => quote! { ::core::option::Option::None },
+                    (None, Some(_)) => quote! { ::serde_catch_all::__private::RawPayload::null() },
+                    (None, None) => from_content(
+                        quote! { ::serde_catch_all::__private::Content::Unit },
+                        TokenStream::new(),
+                        quote! { __E },
+                    ),
+                };
+                quote! { #member: #unit, }
+            });
+
+            let map_arms = known_variants.iter().enumerate().map(|(index, v)| {
+                let path = &v.path;
+                let body = match &v.shape {
+                    Shape::Unit => quote! {{
+                        map.next_value::<()>()?;
+                        #path
+                    }},
+                    Shape::Newtype(_) => quote! { #path(map.next_value()?) },
+                    Shape::Tuple(tys) => {
+                        let slots = slots(tys.len());
+                        quote! {{
+                            let (#(#slots,)*): (#(#tys,)*) = map.next_value()?;
+                            #path(#(#slots),*)
+                        }}
+                    }
+                    Shape::Struct(fields) => {
+                        let build = struct_from_entries(
+                            path,
+                            fields,
+                            Some(quote! { &name }),
+                            quote! { __A::Error },
+                        );
+                        quote! {{
+                            let rest = ::serde_catch_all::__private::next_entries(&mut map)?;
+                            #build
+                        }}
+                    }
+                };
+                quote! { ::core::option::Option::Some(#index) => #body, }
+            });
+            let map_unknown = match rest_member {
+                Some(member) => {
+                    let value = match bare_payload {
+                        Some(_) => quote! { ::core::option::Option::Some(map.next_value()?) },
+                        None => quote! { map.next_value()? },
+                    };
+                    new_catch_all(quote! { name }, Some(quote! { #member: #value, }))
+                }
+                None => {
+                    let catch_all = new_catch_all(quote! { name }, None);
+                    quote! {{
+                        map.next_value::<::serde::de::IgnoredAny>()?;
+                        #catch_all
+                    }}
+                }
+            };
+
+            let bare_unknown = unknown(
+                new_catch_all(
+                    quote! { ::serde_catch_all::__private::String::from(v) },
+                    bare_rest_init,
+                ),
+                quote! { v },
+                quote! { __E },
+            );
+            let map_unknown = unknown(map_unknown, quote! { &name }, quote! { __A::Error });
+
+            let ser_arms = known_variants.iter().map(|v| {
+                let path = &v.path;
+                let name = &v.serialize_name;
+                let Some((pattern, payload)) = payload(v) else {
+                    return quote! { #path => serializer.serialize_str(#name), };
+                };
+                quote! {
+                    #pattern => {
+                        let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                        map.serialize_entry(#name, #payload)?;
+                        map.end()
+                    }
+                }
+            });
+            // An unknown name read without a payload goes back out as a bare string, which only an
+            // `Option` payload remembers
+            let catch_all_ser = match (rest_member, bare_payload) {
+                (Some(member), Some(_)) => quote! {
+                    #catch_all_path { #name_member: name, #member: rest } => {
+                        let name = ::core::convert::AsRef::<str>::as_ref(name);
+                        match rest {
+                            ::core::option::Option::None => serializer.serialize_str(name),
+                            ::core::option::Option::Some(rest) => {
+                                let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                                map.serialize_entry(name, rest)?;
+                                map.end()
+                            }
+                        }
+                    }
+                },
+                (Some(member), None) => quote! {
+                    #catch_all_path { #name_member: name, #member: rest } => {
+                        let mut map = serializer.serialize_map(::core::option::Option::Some(1))?;
+                        map.serialize_entry(::core::convert::AsRef::<str>::as_ref(name), rest)?;
+                        map.end()
+                    }
+                },
+                (None, _) => quote! {
+                    #catch_all_pat => serializer.serialize_str(#catch_all_name),
+                },
+            };
+
+            // Marked for formats that are not self-describing, those that are not human-readable
+            // get serde's enum form instead, with the variant as its index and every payload as a
+            // newtype. The catch-all comes last, holding the name and the rest as a pair. An
+            // unknown name from a format that writes names takes its payload as is.
+            let enum_name = enum_ident.unraw().to_string();
+            let catch_all_index = known_variants.len() as u32;
+            let catch_all_variant = match catch_all_unit {
+                Some(name) => name.value.clone(),
+                None => catch_all_path
+                    .segments
+                    .last()
+                    .expect("variant paths end in the variant")
+                    .ident
+                    .unraw()
+                    .to_string(),
+            };
+            let variant_names = known_variants
+                .iter()
+                .map(|v| &v.deserialize_name.value)
+                .chain([&catch_all_variant]);
+            let enum_arms = known_variants.iter().enumerate().map(|(index, v)| {
+                let path = &v.path;
+                let name = &v.serialize_name;
+                let body = match &v.shape {
+                    _ if v.skip_deserializing => quote! {
+                        return ::core::result::Result::Err(
+                            ::serde::de::Error::unknown_variant(#name, &[]),
+                        )
+                    },
+                    Shape::Unit => quote! {{
+                        ::serde::de::VariantAccess::unit_variant(variant)?;
+                        #path
+                    }},
+                    Shape::Newtype(_) => {
+                        quote! { #path(::serde::de::VariantAccess::newtype_variant(variant)?) }
+                    }
+                    Shape::Tuple(tys) => {
+                        let slots = slots(tys.len());
+                        quote! {{
+                            let (#(#slots,)*): (#(#tys,)*) = ::serde::de::VariantAccess::newtype_variant(variant)?;
+                            #path(#(#slots),*)
+                        }}
+                    }
+                    Shape::Struct(fields) => {
+                        let idents = fields.iter().map(|field| &field.ident);
+                        let tys = fields.iter().map(|field| &field.ty);
+                        let slots = slots(fields.len());
+                        quote! {{
+                            let (#(#slots,)*): (#(#tys,)*) = ::serde::de::VariantAccess::newtype_variant(variant)?;
+                            #path { #(#idents: #slots),* }
+                        }}
+                    }
+                };
+                quote! { ::core::result::Result::Ok(#index) => #body, }
+            });
+            let enum_unknown = match (catch_all_unit, catch_all_rest) {
+                (Some(_), _) => {
+                    let catch_all = unknown(
+                        new_catch_all(quote! { name }, None),
+                        quote! { #catch_all_name },
+                        quote! { __A::Error },
+                    );
+                    quote! {{
+                        ::serde::de::VariantAccess::unit_variant(variant)?;
+                        #catch_all
+                    }}
+                }
+                (None, Some((member, ty))) => {
+                    let catch_all = unknown(
+                        new_catch_all(quote! { name }, Some(quote! { #member: rest, })),
+                        quote! { &name },
+                        quote! { __A::Error },
+                    );
+                    quote! {{
+                        let (name, rest): (::serde_catch_all::__private::String, #ty) =
+                            ::serde::de::VariantAccess::newtype_variant(variant)?;
+                        #catch_all
+                    }}
+                }
+                (None, None) => {
+                    let catch_all = unknown(
+                        new_catch_all(quote! { name }, None),
+                        quote! { &name },
+                        quote! { __A::Error },
+                    );
+                    quote! {{
+                        let name: ::serde_catch_all::__private::String =
+                            ::serde::de::VariantAccess::newtype_variant(variant)?;
+                        #catch_all
+                    }}
+                }
+            };
+            let named_unknown = {
+                let catch_all = match catch_all_rest {
+                    Some((member, _)) if catch_all_unit.is_none() => {
+                        new_catch_all(quote! { name }, Some(quote! { #member: rest, }))
+                    }
+                    _ => new_catch_all(quote! { name }, None),
+                };
+                let catch_all = unknown(catch_all, quote! { &name }, quote! { __A::Error });
+                match catch_all_rest {
+                    Some((_, ty)) if catch_all_unit.is_none() => quote! {{
+                        let rest: #ty = ::serde::de::VariantAccess::newtype_variant(variant)?;
+                        #catch_all
+                    }},
+                    _ => quote! {{
+                        ::serde::de::VariantAccess::unit_variant(variant)?;
+                        #catch_all
+                    }},
+                }
+            };
+            let compact_ser_arms = known_variants.iter().enumerate().map(|(index, v)| {
+                let index = index as u32;
+                let name = &v.serialize_name;
+                let Some((pattern, payload)) = compact_payload(v) else {
+                    let path = &v.path;
+                    return quote! {
+                        #path => serializer.serialize_unit_variant(#enum_name, #index, #name),
+                    };
+                };
+                quote! {
+                    #pattern => serializer.serialize_newtype_variant(#enum_name, #index, #name, #payload),
+                }
+            });
+            let compact_catch_all_ser = match (catch_all_unit, rest_member) {
+                (Some(_), _) => quote! {
+                    #catch_all_pat => serializer.serialize_unit_variant(#enum_name, #catch_all_index, #catch_all_variant),
+                },
+                (None, Some(member)) => quote! {
+                    #catch_all_path { #name_member: name, #member: rest } => serializer.serialize_newtype_variant(
+                        #enum_name,
+                        #catch_all_index,
+                        #catch_all_variant,
+                        &(::core::convert::AsRef::<str>::as_ref(name), rest),
+                    ),
+                },
+                (None, None) => quote! {
+                    #catch_all_pat => serializer.serialize_newtype_variant(#enum_name, #catch_all_index, #catch_all_variant, #catch_all_name),
+                },
+            };
+
+            let (variants, visit_enum, de_call, ser_body) = if options.non_self_describing {
+                (
+                    Some(quote! { const VARIANTS: &[&str] = &[#(#variant_names),*]; }),
+                    Some(quote! {
+                        fn visit_enum<__A>(self, data: __A) -> ::core::result::Result<Self::Value, __A::Error>
+                        where
+                            __A: ::serde::de::EnumAccess<'de>,
+                        {
+                            let (index, variant) = data.variant_seed(
+                                ::serde_catch_all::__private::VariantIndex {
+                                    variants: VARIANTS,
+                                    lookup: #lookup,
+                                },
+                            )?;
+                            ::core::result::Result::Ok(match index {
+                                #(#enum_arms)*
+                                ::core::result::Result::Ok(_) => #enum_unknown,
+                                ::core::result::Result::Err(name) => #named_unknown,
+                            })
+                        }
+                    }),
+                    quote! {
+                        if deserializer.is_human_readable() {
+                            deserializer.deserialize_any(visitor)
+                        } else {
+                            deserializer.deserialize_enum(#enum_name, VARIANTS, visitor)
+                        }
+                    },
+                    quote! {
+                        if serializer.is_human_readable() {
+                            match self {
+                                #(#ser_arms)*
+                                #catch_all_ser
+                            }
+                        } else {
+                            match self {
+                                #(#compact_ser_arms)*
+                                #compact_catch_all_ser
+                            }
+                        }
+                    },
+                )
+            } else {
+                (
+                    None,
+                    None,
+                    quote! { deserializer.deserialize_any(visitor) },
+                    quote! {
+                        match self {
+                            #(#ser_arms)*
+                            #catch_all_ser
+                        }
+                    },
+                )
+            };
+
+            (
+                quote! {
+                    #variants
+
+                    struct __Visitor #de_impl_generics #de_where_clause {
+                        marker: ::core::marker::PhantomData<#enum_ident #ty_generics>,
+                        lifetime: ::core::marker::PhantomData<&'de ()>,
+                    }
+
+                    impl #de_impl_generics ::serde::de::Visitor<'de> for __Visitor #de_ty_generics #de_where_clause {
+                        type Value = #enum_ident #ty_generics;
+
+                        fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                            f.write_str("a variant name or a map with a single variant")
+                        }
+
+                        // A strict enum without unit variants rejects every bare name
+                        #[allow(unreachable_code)]
+                        fn visit_str<__E>(self, v: &str) -> ::core::result::Result<Self::Value, __E>
+                        where
+                            __E: ::serde::de::Error,
+                        {
+                            ::core::result::Result::Ok(match #lookup(v) {
+                                #(#bare_arms)*
+                                #bare_data_arm
+                                ::core::option::Option::None => #bare_unknown,
+                            })
+                        }
+
+                        #visit_enum
+
+                        fn visit_map<__A>(self, mut map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+                        where
+                            __A: ::serde::de::MapAccess<'de>,
+                        {
+                            let name: ::serde_catch_all::__private::String = match map.next_key()? {
+                                ::core::option::Option::Some(name) => name,
+                                ::core::option::Option::None => {
+                                    return ::core::result::Result::Err(
+                                        ::serde::de::Error::invalid_length(0, &self),
+                                    );
+                                }
+                            };
+                            let value = match #lookup(&name) {
+                                #(#map_arms)*
+                                _ => #map_unknown,
+                            };
+                            if map.next_key::<::serde::de::IgnoredAny>()?.is_some() {
+                                return ::core::result::Result::Err(
+                                    ::serde::de::Error::invalid_length(2, &self),
+                                );
+                            }
+                            ::core::result::Result::Ok(value)
+                        }
+                    }
+
+                    let visitor = __Visitor {
+                        marker: ::core::marker::PhantomData,
+                        lifetime: ::core::marker::PhantomData,
+                    };
+                    #de_call
+                },
+                ser_body,
+            )
+        }
+    };
+
+    let as_str_arms = known_variants.iter().map(|v| {
+        let name = &v.serialize_name;
+        let path = &v.path;
+        quote! { #path { .. } => #name, }
+    });
+
+    // Like for plain enums, the introspection items stay unless only `Serialize` is derived
+    let unit_paths = known_variants
+        .iter()
+        .filter(|v| matches!(v.shape, Shape::Unit) && v.is_listed())
+        .map(|v| &v.path);
+    let introspection = (emit.item || emit.deserialize).then(|| {
+        quote! {
+            impl #impl_generics #enum_ident #ty_generics #where_clause {
+                #introspection
+
+                /// Iterates over every known unit variant that is not skipped both ways, in
+                /// declaration order. Variants with data have no value to give.
+                #enum_vis fn known_variants() -> impl ::core::iter::Iterator<Item = Self> {
+                    [#(#unit_paths),*].into_iter()
+                }
+            }
+        }
+    });
+
+    let deserialize = emit.deserialize.then(|| quote! {
+        impl #impl_generics #enum_ident #ty_generics #where_clause {
+            fn __serde_catch_all_variant(v: &str) -> ::core::option::Option<usize> {
+                #lookup_body
+            }
+        }
+
+        impl #de_impl_generics ::serde::Deserialize<'de> for #enum_ident #ty_generics #de_where_clause {
+            fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                #deserialize_body
+            }
+        }
+
+        #support_items
+    });
+
+    let serialize = emit.serialize.then(|| quote! {
+        impl #impl_generics #enum_ident #ty_generics #base_where_clause {
+            /// Returns the name of this value's variant, which for the catch-all variant is the
+            /// captured name.
+            #enum_vis fn as_str(&self) -> &str {
+                match self {
+                    #(#as_str_arms)*
+                    #catch_all_pat => #catch_all_name,
+                }
+            }
+        }
+
+        impl #impl_generics ::serde::Serialize for #enum_ident #ty_generics #ser_where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                use ::serde::ser::SerializeMap as _;
+
+                #skipped_serializing_check
+                #serialize_body
+            }
+        }
+    });
+
+    quote! {
+        // Keep the user's enum but without problematic attributes
+        #cleaned_input
+        #observer_check
+        #introspection
+        #deserialize
+        #serialize
+    }
+}
+
+/// A catch-all payload of raw JSON, which is forwarded without being parsed.
+pub enum RawPayload {
+    /// `Box<RawValue>`
+    Boxed,
+    /// `&'a RawValue`, borrowing for the given lifetime
+    Borrowed(syn::Lifetime),
+}
+
+/// Recognizes `Box<RawValue>` and `&'a RawValue` from `serde_json` by name, when the
+/// `serde_json` feature is enabled.
+pub fn raw_payload(ty: &syn::Type) -> Option<RawPayload> {
+    fn is_raw_value(ty: &syn::Type) -> bool {
+        matches!(ty, syn::Type::Path(tp) if tp.qself.is_none()
+            && tp.path.segments.last().is_some_and(|last| last.ident == "RawValue"))
+    }
+
+    if !cfg!(feature = "serde_json") {
+        return None;
+    }
+    match ty {
+        syn::Type::Reference(r) if r.mutability.is_none() && is_raw_value(&r.elem) => {
+            r.lifetime.clone().map(RawPayload::Borrowed)
+        }
+        syn::Type::Path(tp) => {
+            let last = tp.path.segments.last()?;
+            let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+                return None;
+            };
+            match args.args.iter().collect::<Vec<_>>().as_slice() {
+                [syn::GenericArgument::Type(inner)]
+                    if last.ident == "Box" && is_raw_value(inner) =>
+                {
+                    Some(RawPayload::Boxed)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// The `T` of an `Option<T>`, recognized by name.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(tp) = ty else {
+        return None;
+    };
+    let last = tp.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>().as_slice() {
+        [syn::GenericArgument::Type(inner)] if tp.qself.is_none() && last.ident == "Option" => {
+            Some(inner)
+        }
+        _ => None,
+    }
+}
+
+// The pattern binding a data variant's fields and an expression serializing them as its
+// payload: the value of a newtype, a tuple of the fields, or a map of named fields. `None` for
+// unit variants.
+fn payload(v: &KnownVariant) -> Option<(TokenStream, TokenStream)> {
+    let path = &v.path;
+    match &v.shape {
+        Shape::Unit => None,
+        Shape::Newtype(_) => Some((quote! { #path(inner) }, quote! { inner })),
+        Shape::Tuple(tys) => {
+            let slots = slots(tys.len());
+            Some((quote! { #path(#(#slots),*) }, quote! { &(#(#slots,)*) }))
+        }
+        Shape::Struct(fields) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            let slots = slots(fields.len());
+            let list =
+                fields
+                    .iter()
+                    .zip(&slots)
+                    .rev()
+                    .fold(quote! { () }, |list, (field, slot)| {
+                        let name = &field.name;
+                        quote! { (#name, #slot, #list) }
+                    });
+            Some((
+                quote! { #path { #(#idents: #slots),* } },
+                quote! { &::serde_catch_all::__private::Fields(#list) },
+            ))
+        }
+    }
+}
+
+// Like `payload`, but with a struct variant's fields in order like a tuple's, which is how
+// formats that are not self-describing write them.
+fn compact_payload(v: &KnownVariant) -> Option<(TokenStream, TokenStream)> {
+    let Shape::Struct(fields) = &v.shape else {
+        return payload(v);
+    };
+    let path = &v.path;
+    let idents = fields.iter().map(|field| &field.ident);
+    let slots = slots(fields.len());
+    Some((
+        quote! { #path { #(#idents: #slots),* } },
+        quote! { &(#(#slots,)*) },
+    ))
+}
+
+fn slots(len: usize) -> Vec<syn::Ident> {
+    (0..len).map(|i| format_ident!("__field{}", i)).collect()
+}
+
+// Deserializes a field from a buffered `Content` value sitting `keys` below the enum, failing
+// with `error`.
+fn from_content(value: TokenStream, keys: TokenStream, error: TokenStream) -> TokenStream {
+    quote! {
+        ::serde_catch_all::__private::replay::<_, #error>(#value, &[#keys])?
+    }
+}
+
+// Builds a struct variant from the buffered entries in `rest`, matching them to fields by name.
+// Unknown entries are skipped and missing `Option` fields become `None`, as with serde. The
+// entries sit below the enum under the `outer` key, if any.
+fn struct_from_entries(
+    path: &syn::Path,
+    fields: &[StructField],
+    outer: Option<TokenStream>,
+    error: TokenStream,
+) -> TokenStream {
+    let slots = slots(fields.len());
+    let tys = fields.iter().map(|field| &field.ty);
+    let outer = outer.iter();
+    let value = from_content(quote! { value }, quote! { #(#outer,)* key }, error);
+    let key_arms = fields.iter().zip(&slots).map(|(field, slot)| {
+        let name = &field.deserialize_name;
+        let aliases = &field.aliases;
+        let pattern = if aliases.is_empty() {
+            quote! { #name }
+        } else {
+            quote! { (#name #(| #aliases)*) }
+        };
+        quote! {
+            ::core::option::Option::Some(key @ #pattern) => {
+                if #slot.is_some() {
+                    return ::core::result::Result::Err(
+                        ::serde::de::Error::duplicate_field(#name),
+                    );
+                }
+                #slot = ::core::option::Option::Some(#value);
+            }
+        }
+    });
+    let inits = fields.iter().zip(&slots).map(|(field, slot)| {
+        let ident = &field.ident;
+        let name = &field.deserialize_name;
+        quote! {
+            #ident: match #slot {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => {
+                    ::serde_catch_all::__private::missing_field(#name)?
+                }
+            },
+        }
+    });
+
+    quote! {{
+        #(let mut #slots: ::core::option::Option<#tys> = ::core::option::Option::None;)*
+        for (key, value) in rest {
+            match key.as_str() {
+                #(#key_arms)*
+                _ => {}
+            }
+        }
+        #path { #(#inits)* }
+    }}
+}
@@ -0,0 +1,371 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, Deserialize, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+/// An owned copy of any value a self-describing deserializer produces.
+///
+/// Use it as the payload of a catch-all variant to keep unknown data without tying the enum to
+/// one format's value type: whatever JSON, CBOR, MessagePack or YAML hands over is recorded
+/// as-is, serializes back to any serializer, and can later be read as a typed value with
+/// [`into_deserializer`](Content::into_deserializer). Tagged enums also use it internally to
+/// hold on to fields that arrive before the tag.
+///
+/// Filling a `Content` goes through `deserialize_any`, so formats that are not self-describing,
+/// such as bincode, cannot produce one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Content {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    /// An absent optional value.
+    None,
+    /// A present optional value, for formats that tell it apart from the bare value.
+    Some(Box<Content>),
+    Unit,
+    /// The value inside a newtype struct, for formats that mark one.
+    Newtype(Box<Content>),
+    Seq(Vec<Content>),
+    /// Map entries in the order they were read, keys included as values of their own.
+    Map(Vec<(Content, Content)>),
+}
+
+impl Content {
+    /// The string this holds, if any.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Content::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether this is `Unit`, which is what a bare unknown name of an externally tagged enum
+    /// leaves as its payload.
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Content::Unit)
+    }
+
+    /// A deserializer replaying this value, for reading it as a typed value:
+    /// `T::deserialize(content.into_deserializer::<serde_json::Error>())`.
+    ///
+    /// The same as the [`IntoDeserializer`] impl, without having to import the trait.
+    pub fn into_deserializer<E: de::Error>(self) -> ContentDeserializer<E> {
+        ContentDeserializer {
+            content: self,
+            error: PhantomData,
+        }
+    }
+
+    pub(crate) fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Content::Bool(b) => Unexpected::Bool(*b),
+            Content::U8(n) => Unexpected::Unsigned(u64::from(*n)),
+            Content::U16(n) => Unexpected::Unsigned(u64::from(*n)),
+            Content::U32(n) => Unexpected::Unsigned(u64::from(*n)),
+            Content::U64(n) => Unexpected::Unsigned(*n),
+            Content::I8(n) => Unexpected::Signed(i64::from(*n)),
+            Content::I16(n) => Unexpected::Signed(i64::from(*n)),
+            Content::I32(n) => Unexpected::Signed(i64::from(*n)),
+            Content::I64(n) => Unexpected::Signed(*n),
+            Content::F32(n) => Unexpected::Float(f64::from(*n)),
+            Content::F64(n) => Unexpected::Float(*n),
+            Content::Char(c) => Unexpected::Char(*c),
+            Content::String(s) => Unexpected::Str(s),
+            Content::Bytes(b) => Unexpected::Bytes(b),
+            Content::None | Content::Some(_) => Unexpected::Option,
+            Content::Unit => Unexpected::Unit,
+            Content::Newtype(_) => Unexpected::NewtypeStruct,
+            Content::Seq(_) => Unexpected::Seq,
+            Content::Map(_) => Unexpected::Map,
+        }
+    }
+}
+
+impl Serialize for Content {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Content::Bool(b) => serializer.serialize_bool(*b),
+            Content::U8(n) => serializer.serialize_u8(*n),
+            Content::U16(n) => serializer.serialize_u16(*n),
+            Content::U32(n) => serializer.serialize_u32(*n),
+            Content::U64(n) => serializer.serialize_u64(*n),
+            Content::I8(n) => serializer.serialize_i8(*n),
+            Content::I16(n) => serializer.serialize_i16(*n),
+            Content::I32(n) => serializer.serialize_i32(*n),
+            Content::I64(n) => serializer.serialize_i64(*n),
+            Content::F32(n) => serializer.serialize_f32(*n),
+            Content::F64(n) => serializer.serialize_f64(*n),
+            Content::Char(c) => serializer.serialize_char(*c),
+            Content::String(s) => serializer.serialize_str(s),
+            Content::Bytes(b) => serializer.serialize_bytes(b),
+            Content::None => serializer.serialize_none(),
+            Content::Some(inner) => serializer.serialize_some(&**inner),
+            Content::Unit => serializer.serialize_unit(),
+            Content::Newtype(inner) => serializer.serialize_newtype_struct("Content", &**inner),
+            Content::Seq(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Content::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Content, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i8<E>(self, v: i8) -> Result<Content, E> {
+        Ok(Content::I8(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> Result<Content, E> {
+        Ok(Content::I16(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Content, E> {
+        Ok(Content::I32(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Content, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_u8<E>(self, v: u8) -> Result<Content, E> {
+        Ok(Content::U8(v))
+    }
+
+    fn visit_u16<E>(self, v: u16) -> Result<Content, E> {
+        Ok(Content::U16(v))
+    }
+
+    fn visit_u32<E>(self, v: u32) -> Result<Content, E> {
+        Ok(Content::U32(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Content, E> {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> Result<Content, E> {
+        Ok(Content::F32(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Content, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_char<E>(self, v: char) -> Result<Content, E> {
+        Ok(Content::Char(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Content, E> {
+        Ok(Content::String(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Content, E> {
+        Ok(Content::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Content, E> {
+        Ok(Content::Bytes(v.into()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Content, E> {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Content, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Content, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Content::deserialize(deserializer).map(|inner| Content::Some(Box::new(inner)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Content, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Content, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Content::deserialize(deserializer).map(|inner| Content::Newtype(Box::new(inner)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Content, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Content::Seq(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Content, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Content::Map(entries))
+    }
+}
+
+impl<'de, E: de::Error> IntoDeserializer<'de, E> for Content {
+    type Deserializer = ContentDeserializer<E>;
+
+    fn into_deserializer(self) -> ContentDeserializer<E> {
+        Content::into_deserializer(self)
+    }
+}
+
+/// Replays a [`Content`] into any `Deserialize` type, failing with the error type `E`.
+pub struct ContentDeserializer<E> {
+    content: Content,
+    error: PhantomData<E>,
+}
+
+impl<'de, E: de::Error> Deserializer<'de> for ContentDeserializer<E> {
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::U8(v) => visitor.visit_u8(v),
+            Content::U16(v) => visitor.visit_u16(v),
+            Content::U32(v) => visitor.visit_u32(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            Content::I8(v) => visitor.visit_i8(v),
+            Content::I16(v) => visitor.visit_i16(v),
+            Content::I32(v) => visitor.visit_i32(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::F32(v) => visitor.visit_f32(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::String(v) => visitor.visit_string(v),
+            Content::Bytes(v) => visitor.visit_byte_buf(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(inner) => visitor.visit_some(inner.into_deserializer()),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(inner) => visitor.visit_newtype_struct(inner.into_deserializer()),
+            Content::Seq(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Content::Map(entries) => {
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::None | Content::Unit => visitor.visit_none(),
+            Content::Some(inner) => visitor.visit_some(inner.into_deserializer()),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Newtype(inner) => visitor.visit_newtype_struct(inner.into_deserializer()),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    // Enums arrive either as a bare variant name or as a single-entry `{variant: value}` map
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Content::Map(entries) if entries.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(entries.into_iter())),
+            ),
+            other => Err(de::Error::invalid_type(other.unexpected(), &"an enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
@@ -0,0 +1,175 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use serde::de::value::{
+    BorrowedStrDeserializer, I64Deserializer, StrDeserializer, U64Deserializer,
+};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+/// A catch-all enum value together with the exact spelling it arrived with.
+///
+/// With an alias, `rename(deserialize = ...)` or a normalized match, deserializing a plain
+/// `#[serde_catch_all]` enum resolves the variant but forgets how it was spelled, and serializing
+/// writes the canonical name back. Wrapping the field in `Lossless` keeps the received string
+/// and serializes it byte-for-byte, while [`value`](Lossless::value) still gives the resolved
+/// variant. The raw string is only stored when it differs from the canonical name.
+///
+/// Deserializing goes through the enum's own `Deserialize`, so strict enums, observers and
+/// `track` treat the value like any other. Serializing likewise fails wherever the enum's own
+/// `Serialize` would, such as for a `#[serde(skip_serializing)]` variant. Works with any enum that
+/// is `AsRef<str>`, which every `#[serde_catch_all]` enum is unless it has a numeric catch-all,
+/// and [`from_raw`] also needs `From<&str>`.
+///
+/// [`from_raw`]: Lossless::from_raw
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Lossless<E> {
+    value: E,
+    raw: Option<String>,
+}
+
+impl<E> Lossless<E>
+where
+    E: for<'a> From<&'a str> + AsRef<str>,
+{
+    /// Resolves `raw` through the enum's `From<&str>`, remembering the spelling. Unlike
+    /// deserializing, this never fails, not even for strict enums, and is not reported to
+    /// observers.
+    pub fn from_raw(raw: &str) -> Self {
+        Lossless::with_raw(E::from(raw), raw)
+    }
+}
+
+impl<E: AsRef<str>> Lossless<E> {
+    // Keeps `raw` only when it is not the canonical spelling of `value`
+    fn with_raw(value: E, raw: &str) -> Self {
+        let raw = (value.as_ref() != raw).then(|| raw.to_owned());
+        Lossless { value, raw }
+    }
+
+    /// Wraps a value with its canonical spelling.
+    pub fn new(value: E) -> Self {
+        Lossless { value, raw: None }
+    }
+
+    /// The resolved value.
+    pub fn value(&self) -> &E {
+        &self.value
+    }
+
+    /// The string exactly as received, which is what gets serialized.
+    pub fn raw(&self) -> &str {
+        self.raw.as_deref().unwrap_or_else(|| self.value.as_ref())
+    }
+
+    /// Whether the value was received with its canonical spelling.
+    pub fn is_canonical(&self) -> bool {
+        self.raw.is_none()
+    }
+
+    /// Drops the original spelling, going back to canonical serialization.
+    pub fn into_value(self) -> E {
+        self.value
+    }
+}
+
+impl<E: AsRef<str>> From<E> for Lossless<E> {
+    fn from(value: E) -> Self {
+        Lossless::new(value)
+    }
+}
+
+impl<E> Deref for Lossless<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.value
+    }
+}
+
+impl<E: AsRef<str>> fmt::Display for Lossless<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.raw())
+    }
+}
+
+impl<E: Serialize + AsRef<str>> Serialize for Lossless<E> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Whether the variant may be written at all, e.g. under `#[serde(skip_serializing)]`, is
+        // up to the enum's own `Serialize`
+        if let Some(message) = crate::__private::serialize_error(&self.value) {
+            return Err(ser::Error::custom(message));
+        }
+        serializer.serialize_str(self.raw())
+    }
+}
+
+impl<'de, E> Deserialize<'de> for Lossless<E>
+where
+    E: Deserialize<'de> + AsRef<str>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The received string is captured here and then resolved by the enum's own
+        // `Deserialize`, so strict mode, observers and `track` all see it
+        struct LosslessVisitor<E>(PhantomData<E>);
+
+        impl<'de, E> Visitor<'de> for LosslessVisitor<E>
+        where
+            E: Deserialize<'de> + AsRef<str>,
+        {
+            type Value = Lossless<E>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string enum")
+            }
+
+            fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err>
+            where
+                Err: de::Error,
+            {
+                let value = E::deserialize(StrDeserializer::<Err>::new(v))?;
+                Ok(Lossless::with_raw(value, v))
+            }
+
+            fn visit_borrowed_str<Err>(self, v: &'de str) -> Result<Self::Value, Err>
+            where
+                Err: de::Error,
+            {
+                let value = E::deserialize(BorrowedStrDeserializer::<Err>::new(v))?;
+                Ok(Lossless::with_raw(value, v))
+            }
+
+            // Discriminants have no spelling to keep
+            fn visit_i64<Err>(self, v: i64) -> Result<Self::Value, Err>
+            where
+                Err: de::Error,
+            {
+                E::deserialize(I64Deserializer::<Err>::new(v)).map(Lossless::new)
+            }
+
+            fn visit_u64<Err>(self, v: u64) -> Result<Self::Value, Err>
+            where
+                Err: de::Error,
+            {
+                E::deserialize(U64Deserializer::<Err>::new(v)).map(Lossless::new)
+            }
+        }
+
+        // `Lossless` is always written as a string, so only self-describing formats can hold a
+        // discriminant instead
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(LosslessVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_str(LosslessVisitor(PhantomData))
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Normalization steps for `#[serde_catch_all(normalize(...))]`.
+//!
+//! Each step takes the incoming string and returns it normalized, borrowing whenever nothing had
+//! to change. Steps run in the order they are listed on the enum, on the input as well as on
+//! the known names, so a value matches a variant when both normalize to the same string. A
+//! custom step passed with `normalize(with = path::to::step)` must have the same signature.
+
+use alloc::borrow::Cow;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+
+/// Lowercases ASCII letters, leaving everything else untouched.
+pub fn ascii_case(s: Cow<'_, str>) -> Cow<'_, str> {
+    if s.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(s.to_ascii_lowercase())
+    } else {
+        s
+    }
+}
+
+/// Lowercases using the full Unicode case mapping. This is what `case_insensitive` enables.
+pub fn unicode_case(s: Cow<'_, str>) -> Cow<'_, str> {
+    if s.is_ascii() {
+        return ascii_case(s);
+    }
+
+    let lower = s.to_lowercase();
+    if lower == *s {
+        s
+    } else {
+        Cow::Owned(lower)
+    }
+}
+
+/// Strips leading and trailing whitespace.
+pub fn trim(s: Cow<'_, str>) -> Cow<'_, str> {
+    match s {
+        Cow::Borrowed(b) => Cow::Borrowed(b.trim()),
+        Cow::Owned(o) if o.trim().len() == o.len() => Cow::Owned(o),
+        Cow::Owned(o) => Cow::Owned(o.trim().to_owned()),
+    }
+}
+
+/// Treats `-`, `_` and spaces as the same separator by turning them all into `_`.
+pub fn separators(s: Cow<'_, str>) -> Cow<'_, str> {
+    if s.contains(['-', ' ']) {
+        Cow::Owned(s.chars().map(separator).collect::<String>())
+    } else {
+        s
+    }
+}
+
+fn separator(c: char) -> char {
+    match c {
+        '-' | ' ' => '_',
+        c => c,
+    }
+}
+
+/// Unicode canonical composition (NFC).
+#[cfg(feature = "unicode")]
+pub fn nfc(s: Cow<'_, str>) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+    match is_nfc_quick(s.chars()) {
+        IsNormalized::Yes => s,
+        _ => Cow::Owned(s.nfc().collect()),
+    }
+}
+
+/// Unicode compatibility composition (NFKC), which also folds look-alikes such as full-width
+/// letters.
+#[cfg(feature = "unicode")]
+pub fn nfkc(s: Cow<'_, str>) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+    match is_nfkc_quick(s.chars()) {
+        IsNormalized::Yes => s,
+        _ => Cow::Owned(s.nfkc().collect()),
+    }
+}
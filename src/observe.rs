@@ -0,0 +1,36 @@
+//! A process-wide observer told about every unknown value that lands in a catch-all.
+
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Called with the name of the enum and the unknown value, e.g. `("Status", "on_hold")`.
+pub type Observer = fn(enum_name: &'static str, raw: &str);
+
+// The registered `Observer` as a data pointer, or null when there is none. Function pointers
+// have no atomic type of their own, and this works without `std` on any target with atomic
+// pointer loads and stores.
+static OBSERVER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Registers `observer` for every enum without an `observe = ...` option of its own, replacing
+/// any observer set before.
+///
+/// It runs each time deserializing an unknown name builds a catch-all variant, so it should be
+/// cheap, like bumping a counter or logging a line. Unknown values rejected by strict enums, or
+/// converted with `From` and `FromStr`, are not reported.
+pub fn set_observer(observer: Observer) {
+    OBSERVER.store(observer as *mut (), Ordering::Release);
+}
+
+/// Removes the observer registered with [`set_observer`], if any.
+pub fn clear_observer() {
+    OBSERVER.store(ptr::null_mut(), Ordering::Release);
+}
+
+pub(crate) fn notify(enum_name: &'static str, raw: &str) {
+    let observer = OBSERVER.load(Ordering::Acquire);
+    if !observer.is_null() {
+        // SAFETY: the only non-null values ever stored are `Observer`s, see `set_observer`
+        let observer = unsafe { core::mem::transmute::<*mut (), Observer>(observer) };
+        observer(enum_name, raw);
+    }
+}
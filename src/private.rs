@@ -0,0 +1,1086 @@
+//! Support code for the expanded macros. Not public API.
+
+pub use alloc::borrow::{Cow, ToOwned};
+#[cfg(feature = "serde_json")]
+use alloc::boxed::Box;
+pub use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, MapAccess,
+    VariantAccess, Visitor,
+};
+use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+
+pub use crate::content::{Content, ContentDeserializer};
+#[cfg(feature = "serde_json")]
+pub use serde_json;
+#[cfg(feature = "serde_json")]
+use serde_json::value::RawValue;
+
+/// Reports an unknown name landing in the catch-all of `enum_name` to the enum's own observer,
+/// or else the global one, and to a running `track` call.
+#[inline]
+pub fn observe_unknown(enum_name: &'static str, raw: &str, observer: Option<crate::Observer>) {
+    match observer {
+        Some(observer) => observer(enum_name, raw),
+        None => crate::observe::notify(enum_name, raw),
+    }
+    #[cfg(feature = "std")]
+    crate::track::record(enum_name, raw);
+}
+
+/// Deserializes buffered content that sits `keys` below the value being read, so that a running
+/// `track` call reports catch-all hits inside it at their own path.
+pub fn replay<'de, T, E>(content: Content, keys: &[&str]) -> Result<T, E>
+where
+    T: Deserialize<'de>,
+    E: de::Error,
+{
+    replay_seed(keys, PhantomData, content.into_deserializer())
+}
+
+#[cfg(feature = "std")]
+use crate::track::replay as replay_seed;
+
+#[cfg(not(feature = "std"))]
+fn replay_seed<'de, S, D>(_keys: &[&str], seed: S, deserializer: D) -> Result<S::Value, D::Error>
+where
+    S: DeserializeSeed<'de>,
+    D: Deserializer<'de>,
+{
+    seed.deserialize(deserializer)
+}
+
+/// The entries of a buffered map, in input order.
+pub type Entries = Vec<(Content, Content)>;
+
+/// Reads a map, pulling out the string value of `tag` and buffering every other entry.
+pub fn take_tag<'de, D>(deserializer: D, tag: &'static str) -> Result<(String, Entries), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct TagVisitor {
+        tag: &'static str,
+    }
+
+    impl<'de> Visitor<'de> for TagVisitor {
+        type Value = (String, Entries);
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a map with a `{}` field", self.tag)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut tag = None;
+            let mut rest = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+            while let Some(key) = map.next_key::<Content>()? {
+                if key.as_str() == Some(self.tag) {
+                    if tag.is_some() {
+                        return Err(de::Error::duplicate_field(self.tag));
+                    }
+                    tag = Some(map.next_value::<String>()?);
+                } else {
+                    rest.push((key, map.next_value()?));
+                }
+            }
+            let tag = tag.ok_or_else(|| de::Error::missing_field(self.tag))?;
+            Ok((tag, rest))
+        }
+    }
+
+    deserializer.deserialize_map(TagVisitor { tag })
+}
+
+/// An adjacently tagged enum, built from its tag and a deserializer for its content.
+pub trait AdjacentlyTagged<'de>: Sized {
+    fn from_content<D>(tag: String, content: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+// Builds an adjacently tagged enum from the content of its `tag`.
+struct ContentSeed<T> {
+    tag: String,
+    marker: PhantomData<T>,
+}
+
+impl<T> ContentSeed<T> {
+    fn new(tag: String) -> Self {
+        ContentSeed {
+            tag,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: AdjacentlyTagged<'de>> DeserializeSeed<'de> for ContentSeed<T> {
+    type Value = T;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::from_content(self.tag, deserializer)
+    }
+}
+
+/// Holds adjacently tagged content that arrived before the tag, to be replayed once the tag is
+/// known.
+pub trait Buffer<'de, T>: Deserialize<'de> {
+    /// Builds `T` from the buffered content under the `content` key, or from a missing one.
+    fn replay<E: de::Error>(buffer: Option<Self>, tag: String, content: &str) -> Result<T, E>;
+}
+
+impl<'de, T: AdjacentlyTagged<'de>> Buffer<'de, T> for Content {
+    fn replay<E: de::Error>(buffer: Option<Self>, tag: String, content: &str) -> Result<T, E> {
+        let buffer = buffer.unwrap_or(Content::Unit).into_deserializer();
+        replay_seed(&[content], ContentSeed::new(tag), buffer)
+    }
+}
+
+// Raw payloads buffer raw JSON instead, so that an unknown tag keeps it byte-for-byte. Owned raw
+// JSON cannot lend out its contents for `'de`, so known variants are replayed through `Content`.
+
+/// An adjacently tagged enum whose catch-all keeps its content as `Box<RawValue>`.
+#[cfg(feature = "serde_json")]
+pub trait RawCatchAll<'de>: Sized {
+    /// Whether `tag` goes straight into the catch-all rather than being replayed.
+    fn keeps_raw(tag: &str) -> bool;
+
+    /// The catch-all for an unknown `tag`, keeping its content as received.
+    fn catch_all(tag: String, raw: Box<RawValue>) -> Self;
+}
+
+#[cfg(feature = "serde_json")]
+impl<'de, T> Buffer<'de, T> for Box<RawValue>
+where
+    T: AdjacentlyTagged<'de> + RawCatchAll<'de>,
+{
+    fn replay<E: de::Error>(buffer: Option<Self>, tag: String, content: &str) -> Result<T, E> {
+        let raw = buffer.unwrap_or_else(RawPayload::null);
+        if T::keeps_raw(&tag) {
+            return Ok(T::catch_all(tag, raw));
+        }
+        let buffer: Content = serde_json::from_str(raw.get()).map_err(de::Error::custom)?;
+        replay_seed(
+            &[content],
+            ContentSeed::new(tag),
+            buffer.into_deserializer(),
+        )
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<'de, T: AdjacentlyTagged<'de>> Buffer<'de, T> for &'de RawValue {
+    fn replay<E: de::Error>(buffer: Option<Self>, tag: String, content: &str) -> Result<T, E> {
+        let buffer = buffer.unwrap_or(RawValue::NULL);
+        replay_seed(&[content], ContentSeed::new(tag), buffer).map_err(de::Error::custom)
+    }
+}
+
+/// Reads a map with a `tag` and a `content` field in either order. Content that arrives after
+/// the tag is deserialized in place, earlier content is buffered as `B`, and missing content is
+/// up to `B`. Other fields are ignored.
+pub fn take_adjacent<'de, T, B, D>(
+    deserializer: D,
+    tag: &'static str,
+    content: &'static str,
+) -> Result<T, D::Error>
+where
+    T: AdjacentlyTagged<'de>,
+    B: Buffer<'de, T>,
+    D: Deserializer<'de>,
+{
+    enum Key {
+        Tag,
+        Content,
+        Other,
+    }
+
+    // Keys are only compared, never kept, and are read as strings so that formats which cannot
+    // deserialize just any value still work
+    #[derive(Clone, Copy)]
+    struct KeySeed {
+        tag: &'static str,
+        content: &'static str,
+    }
+
+    impl<'de> DeserializeSeed<'de> for KeySeed {
+        type Value = Key;
+
+        fn deserialize<D>(self, deserializer: D) -> Result<Key, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_str(self)
+        }
+    }
+
+    impl<'de> Visitor<'de> for KeySeed {
+        type Value = Key;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a field name")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Key, E> {
+            Ok(if v == self.tag {
+                Key::Tag
+            } else if v == self.content {
+                Key::Content
+            } else {
+                Key::Other
+            })
+        }
+    }
+
+    struct AdjacentVisitor<T, B> {
+        tag: &'static str,
+        content: &'static str,
+        marker: PhantomData<(T, B)>,
+    }
+
+    impl<'de, T: AdjacentlyTagged<'de>, B: Buffer<'de, T>> Visitor<'de> for AdjacentVisitor<T, B> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a map with `{}` and `{}` fields", self.tag, self.content)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<T, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut tag = None;
+            let mut buffered = None;
+            let mut value = None;
+            let key_seed = KeySeed {
+                tag: self.tag,
+                content: self.content,
+            };
+            while let Some(key) = map.next_key_seed(key_seed)? {
+                match key {
+                    Key::Tag => {
+                        if tag.is_some() || value.is_some() {
+                            return Err(de::Error::duplicate_field(self.tag));
+                        }
+                        tag = Some(map.next_value::<String>()?);
+                    }
+                    Key::Content => {
+                        if buffered.is_some() || value.is_some() {
+                            return Err(de::Error::duplicate_field(self.content));
+                        }
+                        match tag.take() {
+                            Some(tag) => {
+                                value = Some(map.next_value_seed(ContentSeed::new(tag))?);
+                            }
+                            None => buffered = Some(map.next_value::<B>()?),
+                        }
+                    }
+                    Key::Other => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            match (value, tag) {
+                (Some(value), _) => Ok(value),
+                (None, Some(tag)) => B::replay(buffered, tag, self.content),
+                (None, None) => Err(de::Error::missing_field(self.tag)),
+            }
+        }
+    }
+
+    deserializer.deserialize_map(AdjacentVisitor::<T, B> {
+        tag,
+        content,
+        marker: PhantomData,
+    })
+}
+
+/// A raw JSON catch-all payload.
+#[cfg(feature = "serde_json")]
+pub trait RawPayload {
+    /// The payload of a bare unknown name.
+    fn null() -> Self;
+
+    /// Whether the payload is `null`, in which case it is left out when serializing.
+    fn is_null(&self) -> bool;
+}
+
+#[cfg(feature = "serde_json")]
+impl RawPayload for Box<RawValue> {
+    fn null() -> Self {
+        RawValue::NULL.to_owned()
+    }
+
+    fn is_null(&self) -> bool {
+        self.get() == "null"
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl RawPayload for &RawValue {
+    fn null() -> Self {
+        RawValue::NULL
+    }
+
+    fn is_null(&self) -> bool {
+        self.get() == "null"
+    }
+}
+
+/// The error for an unknown name in a strict enum, suggesting the closest of the `known` names.
+pub fn unknown_variant<E: de::Error>(value: &str, known: &'static [&'static str]) -> E {
+    de::Error::custom(UnknownVariant { value, known })
+}
+
+struct UnknownVariant<'a> {
+    value: &'a str,
+    known: &'static [&'static str],
+}
+
+impl fmt::Display for UnknownVariant<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown variant {:?}", self.value)?;
+
+        // Only names within about a third of the input's length in edits are worth suggesting
+        let limit = (self.value.chars().count() / 3).max(1);
+        let distances: Vec<usize> = self
+            .known
+            .iter()
+            .map(|name| edit_distance(self.value, name))
+            .collect();
+        let (prefix, separator, closest) =
+            match distances.iter().copied().min().filter(|&min| min <= limit) {
+                Some(min) => (", did you mean ", " or ", Some(min)),
+                None => (", expected one of ", ", ", None),
+            };
+        if self.known.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str(prefix)?;
+        let candidates = self
+            .known
+            .iter()
+            .zip(&distances)
+            .filter(|(_, &distance)| closest.is_none_or(|min| distance == min));
+        for (i, (name, _)) in candidates.enumerate() {
+            if i > 0 {
+                f.write_str(separator)?;
+            }
+            write!(f, "{:?}", name)?;
+        }
+        if closest.is_some() {
+            f.write_str("?")?;
+        }
+        Ok(())
+    }
+}
+
+// The Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a == b {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Reads a value that has to be a map as buffered entries.
+pub fn entries<'de, D>(deserializer: D) -> Result<Entries, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Content::deserialize(deserializer)? {
+        Content::Map(entries) => Ok(entries),
+        other => Err(de::Error::invalid_type(other.unexpected(), &"a map")),
+    }
+}
+
+/// Reads the next map value, which has to be a map itself, as buffered entries.
+pub fn next_entries<'de, A>(map: &mut A) -> Result<Entries, A::Error>
+where
+    A: MapAccess<'de>,
+{
+    match map.next_value()? {
+        Content::Map(entries) => Ok(entries),
+        other => Err(de::Error::invalid_type(other.unexpected(), &"a map")),
+    }
+}
+
+/// The value of a field that was not in the input: `None` for options, an error otherwise.
+pub fn missing_field<'de, T, E>(field: &'static str) -> Result<T, E>
+where
+    T: Deserialize<'de>,
+    E: de::Error,
+{
+    struct MissingFieldDeserializer<E> {
+        field: &'static str,
+        error: PhantomData<E>,
+    }
+
+    impl<'de, E: de::Error> Deserializer<'de> for MissingFieldDeserializer<E> {
+        type Error = E;
+
+        fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            Err(de::Error::missing_field(self.field))
+        }
+
+        fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_none()
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf unit unit_struct newtype_struct seq tuple tuple_struct
+            map struct enum identifier ignored_any
+        }
+    }
+
+    T::deserialize(MissingFieldDeserializer {
+        field,
+        error: PhantomData,
+    })
+}
+
+// In formats that are not self-describing, numeric enums that write both names and codes wrap
+// each value in a newtype variant of one of these, so that it can be read back with the right
+// hint.
+const COMPACT_VARIANTS: &[&str] = &["Name", "Signed", "Unsigned"];
+
+#[derive(Clone, Copy)]
+enum CompactKind {
+    Name,
+    Signed,
+    Unsigned,
+}
+
+struct KindVisitor;
+
+impl Visitor<'_> for KindVisitor {
+    type Value = CompactKind;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("`Name`, `Signed` or `Unsigned`")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<CompactKind, E> {
+        match v {
+            0 => Ok(CompactKind::Name),
+            1 => Ok(CompactKind::Signed),
+            2 => Ok(CompactKind::Unsigned),
+            _ => Err(de::Error::invalid_value(de::Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<CompactKind, E> {
+        match v {
+            "Name" => Ok(CompactKind::Name),
+            "Signed" => Ok(CompactKind::Signed),
+            "Unsigned" => Ok(CompactKind::Unsigned),
+            _ => Err(de::Error::unknown_variant(v, COMPACT_VARIANTS)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_identifier(KindVisitor)
+    }
+}
+
+/// Writes a name of a numeric enum in a format that is not self-describing.
+pub fn serialize_compact_name<S: Serializer>(
+    serializer: S,
+    enum_name: &'static str,
+    name: &str,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_variant(enum_name, 0, COMPACT_VARIANTS[0], name)
+}
+
+/// Writes a code of a numeric enum in a format that is not self-describing, as an `i64` if it
+/// fits and a `u64` otherwise.
+pub fn serialize_compact_code<S, C>(
+    serializer: S,
+    enum_name: &'static str,
+    code: C,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    C: Copy + fmt::Display,
+    i64: TryFrom<C>,
+    u64: TryFrom<C>,
+{
+    if let Ok(signed) = i64::try_from(code) {
+        return serializer.serialize_newtype_variant(enum_name, 1, COMPACT_VARIANTS[1], &signed);
+    }
+    match u64::try_from(code) {
+        Ok(unsigned) => {
+            serializer.serialize_newtype_variant(enum_name, 2, COMPACT_VARIANTS[2], &unsigned)
+        }
+        Err(_) => Err(ser::Error::custom(format_args!(
+            "code {} does not fit in an `i64` or `u64`",
+            code
+        ))),
+    }
+}
+
+/// Reads a value written by `serialize_compact_name` or `serialize_compact_code`, handing the
+/// name to `visitor` as a string and the code as an integer.
+pub fn deserialize_compact<'de, D, V>(
+    deserializer: D,
+    enum_name: &'static str,
+    visitor: V,
+) -> Result<V::Value, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Visitor<'de>,
+{
+    // Reads the wrapped value with the hint for its kind
+    struct ValueSeed<V> {
+        kind: CompactKind,
+        visitor: V,
+    }
+
+    impl<'de, V: Visitor<'de>> DeserializeSeed<'de> for ValueSeed<V> {
+        type Value = V::Value;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+            match self.kind {
+                CompactKind::Name => deserializer.deserialize_str(self.visitor),
+                CompactKind::Signed => deserializer.deserialize_i64(self.visitor),
+                CompactKind::Unsigned => deserializer.deserialize_u64(self.visitor),
+            }
+        }
+    }
+
+    struct CompactVisitor<V>(V);
+
+    impl<'de, V: Visitor<'de>> Visitor<'de> for CompactVisitor<V> {
+        type Value = V::Value;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.expecting(f)
+        }
+
+        fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+            let (kind, variant) = data.variant::<CompactKind>()?;
+            variant.newtype_variant_seed(ValueSeed {
+                kind,
+                visitor: self.0,
+            })
+        }
+    }
+
+    deserializer.deserialize_enum(enum_name, COMPACT_VARIANTS, CompactVisitor(visitor))
+}
+
+/// Reads which variant an externally tagged enum holds in serde's enum form: its index in
+/// `variants`, where the catch-all comes last, or the name itself for formats that write names.
+/// Names go through the enum's `lookup`, with its aliases and normalizers, and the unknown ones
+/// come back as `Err`, for the catch-all to take.
+pub struct VariantIndex {
+    pub variants: &'static [&'static str],
+    pub lookup: fn(&str) -> Option<usize>,
+}
+
+impl<'de> DeserializeSeed<'de> for VariantIndex {
+    type Value = Result<usize, String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl Visitor<'_> for VariantIndex {
+    type Value = Result<usize, String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a variant index or name")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match usize::try_from(v) {
+            Ok(index) if index < self.variants.len() => Ok(Ok(index)),
+            _ => Err(de::Error::invalid_value(de::Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if let Some(index) = (self.lookup)(v) {
+            return Ok(Ok(index));
+        }
+        // The catch-all as written by the enum itself, holding its name and payload as a pair
+        match self.variants.split_last() {
+            Some((catch_all, known)) if *catch_all == v => Ok(Ok(known.len())),
+            _ => Ok(Err(v.to_owned())),
+        }
+    }
+}
+
+/// Serializes a struct or map as extra entries of a map that is already open, which is how the
+/// fields of a tagged variant end up next to the tag.
+pub struct FlatMapSerializer<'a, M>(pub &'a mut M);
+
+fn flatten_error<E: ser::Error>() -> E {
+    ser::Error::custom("can only flatten structs, maps and unit values into a tagged enum")
+}
+
+impl<'a, M: SerializeMap> Serializer for FlatMapSerializer<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = FlatMapSerializeMap<'a, M>;
+    type SerializeStruct = FlatMapSerializeStruct<'a, M>;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_char(self, _: char) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_str(self, _: &str) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(flatten_error())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, M::Error> {
+        Ok(FlatMapSerializeMap(self.0))
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, M::Error> {
+        Ok(FlatMapSerializeStruct(self.0))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(flatten_error())
+    }
+}
+
+pub struct FlatMapSerializeMap<'a, M>(&'a mut M);
+
+impl<M: SerializeMap> SerializeMap for FlatMapSerializeMap<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), M::Error> {
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), M::Error> {
+        self.0.serialize_value(value)
+    }
+
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), M::Error>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+pub struct FlatMapSerializeStruct<'a, M>(&'a mut M);
+
+impl<M: SerializeMap> SerializeStruct for FlatMapSerializeStruct<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+/// Serializes a list of `(name, &value, rest)` fields, ending in `()`, as a map. This is how the
+/// struct variants of an externally tagged enum are written out without a struct to derive on.
+pub struct Fields<F>(pub F);
+
+pub trait FieldList {
+    const LEN: usize;
+
+    fn serialize_fields<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error>;
+}
+
+impl FieldList for () {
+    const LEN: usize = 0;
+
+    fn serialize_fields<M: SerializeMap>(&self, _: &mut M) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<T: ?Sized + Serialize, R: FieldList> FieldList for (&'static str, &T, R) {
+    const LEN: usize = 1 + R::LEN;
+
+    fn serialize_fields<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error> {
+        map.serialize_entry(self.0, self.1)?;
+        self.2.serialize_fields(map)
+    }
+}
+
+impl<F: FieldList> Serialize for Fields<F> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(F::LEN))?;
+        self.0.serialize_fields(&mut map)?;
+        map.end()
+    }
+}
+
+/// Whether `value` serializes as nothing but a unit or `None`, in which case the content of an
+/// unknown adjacently tagged variant is left out.
+pub fn is_unit<T: ?Sized + Serialize>(value: &T) -> bool {
+    value.serialize(UnitProbe).unwrap_or(false)
+}
+
+/// The error `value` raises as soon as it is serialized, such as for a `skip_serializing`
+/// variant, found without writing anything.
+pub fn serialize_error<T: ?Sized + Serialize>(value: &T) -> Option<String> {
+    value
+        .serialize(UnitProbe)
+        .err()
+        .and_then(|NotUnit(message)| message)
+}
+
+struct UnitProbe;
+
+// A compound value, which cannot be unit, or the error the value itself raised
+#[derive(Debug)]
+struct NotUnit(Option<String>);
+
+impl fmt::Display for NotUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not a unit value")
+    }
+}
+
+impl ser::StdError for NotUnit {}
+
+impl ser::Error for NotUnit {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        NotUnit(Some(message.to_string()))
+    }
+}
+
+impl Serializer for UnitProbe {
+    type Ok = bool;
+    type Error = NotUnit;
+    type SerializeSeq = Impossible<bool, NotUnit>;
+    type SerializeTuple = Impossible<bool, NotUnit>;
+    type SerializeTupleStruct = Impossible<bool, NotUnit>;
+    type SerializeTupleVariant = Impossible<bool, NotUnit>;
+    type SerializeMap = Impossible<bool, NotUnit>;
+    type SerializeStruct = Impossible<bool, NotUnit>;
+    type SerializeStructVariant = Impossible<bool, NotUnit>;
+
+    fn serialize_bool(self, _: bool) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_char(self, _: char) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_str(self, _: &str) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_none(self) -> Result<bool, NotUnit> {
+        Ok(true)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_unit(self) -> Result<bool, NotUnit> {
+        Ok(true)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<bool, NotUnit> {
+        Ok(true)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<bool, NotUnit> {
+        Ok(false)
+    }
+
+    // Compound values are never unit, so bail out before anything inside gets serialized
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, NotUnit> {
+        Err(NotUnit(None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, NotUnit> {
+        Err(NotUnit(None))
+    }
+}
@@ -0,0 +1,616 @@
+//! Finding out where in a document unknown values show up.
+//!
+//! [`track`] wraps a deserializer so that every map key, sequence index and enum variant it
+//! descends into is pushed onto a per-thread path. The generated impls report catch-all hits
+//! through the same channel as the observers, which records them together with the current path.
+
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use std::thread_local;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, Visitor};
+
+/// Where in a document a value sits, such as `orders[3].status`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+/// One step of a [`Path`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// An element of a sequence.
+    Index(usize),
+    /// The value of a map entry or struct field, or the payload of an enum variant.
+    Key(String),
+    /// The value of a map entry whose key is not a string, integer, bool or char.
+    Unknown,
+}
+
+impl Path {
+    /// The steps from the root of the document, outermost first.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str(".");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                Segment::Key(key) if i == 0 => f.write_str(key)?,
+                Segment::Key(key) => write!(f, ".{}", key)?,
+                Segment::Unknown if i == 0 => f.write_str("?")?,
+                Segment::Unknown => f.write_str(".?")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Tracker {
+    path: Vec<Segment>,
+    hits: Vec<(Path, &'static str, String)>,
+}
+
+thread_local! {
+    static TRACKER: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+}
+
+/// Deserializes a `T` from `deserializer`, calling `callback` with the path, enum name and raw
+/// value of every unknown name that landed in a catch-all, like `orders[3].status`, `"Status"`
+/// and `"on_hold"`.
+///
+/// The hits are reported in input order once deserializing is done, also when it failed part
+/// way. Needs the `std` feature.
+pub fn track<'de, D, T, F>(deserializer: D, mut callback: F) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+    F: FnMut(&Path, &'static str, &str),
+{
+    let outer = TRACKER.with(|tracker| {
+        tracker.replace(Some(Tracker {
+            path: Vec::new(),
+            hits: Vec::new(),
+        }))
+    });
+    let restore = Restore(Some(outer));
+    let result = T::deserialize(Tracked(deserializer));
+    let hits = restore
+        .finish()
+        .map(|tracker| tracker.hits)
+        .unwrap_or_default();
+    for (path, enum_name, raw) in &hits {
+        callback(path, enum_name, raw);
+    }
+    result
+}
+
+// Puts back the tracker of an enclosing `track` call, or none, when this one is done. Dropping
+// it does the same, so that a panic in a `Deserialize` impl cannot leave this call's tracker in
+// place for later ones on the thread.
+struct Restore(Option<Option<Tracker>>);
+
+impl Restore {
+    // Returns this call's tracker.
+    fn finish(mut self) -> Option<Tracker> {
+        self.swap()
+    }
+
+    fn swap(&mut self) -> Option<Tracker> {
+        let outer = self.0.take()?;
+        TRACKER.with(|tracker| tracker.replace(outer))
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        self.swap();
+    }
+}
+
+/// Records a catch-all hit at the current path, if a [`track`] call is running.
+pub(crate) fn record(enum_name: &'static str, raw: &str) {
+    TRACKER.with(|tracker| {
+        if let Some(tracker) = tracker.borrow_mut().as_mut() {
+            let path = Path {
+                segments: tracker.path.clone(),
+            };
+            tracker.hits.push((path, enum_name, raw.to_owned()));
+        }
+    });
+}
+
+// Runs `f` one step further down the current path.
+fn descend<T>(segment: Segment, f: impl FnOnce() -> T) -> T {
+    let pushed = TRACKER.with(|tracker| match tracker.borrow_mut().as_mut() {
+        Some(tracker) => {
+            tracker.path.push(segment);
+            true
+        }
+        None => false,
+    });
+    let value = f();
+    if pushed {
+        TRACKER.with(|tracker| {
+            if let Some(tracker) = tracker.borrow_mut().as_mut() {
+                tracker.path.pop();
+            }
+        });
+    }
+    value
+}
+
+/// Replays buffered content below the current path, `keys` further down, so that a running
+/// [`track`] call follows it as if it had been read in place.
+pub(crate) fn replay<'de, S, D>(
+    keys: &[&str],
+    seed: S,
+    deserializer: D,
+) -> Result<S::Value, D::Error>
+where
+    S: DeserializeSeed<'de>,
+    D: Deserializer<'de>,
+{
+    if TRACKER.with(|tracker| tracker.borrow().is_none()) {
+        return seed.deserialize(deserializer);
+    }
+    match keys.split_first() {
+        Some((key, keys)) => descend(Segment::Key((*key).to_owned()), || {
+            replay(keys, seed, deserializer)
+        }),
+        None => seed.deserialize(Tracked(deserializer)),
+    }
+}
+
+// Forwards every `deserialize_*` method to the inner deserializer, with the visitor wrapped by
+// the `split` method of `Self`.
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let (inner, visitor) = self.split(visitor);
+                inner.$method($($arg,)* visitor)
+            }
+        )*
+
+        fn is_human_readable(&self) -> bool {
+            self.0.is_human_readable()
+        }
+    };
+}
+
+macro_rules! deserialize_methods {
+    () => {
+        forward_deserialize! {
+            deserialize_any()
+            deserialize_bool()
+            deserialize_i8()
+            deserialize_i16()
+            deserialize_i32()
+            deserialize_i64()
+            deserialize_i128()
+            deserialize_u8()
+            deserialize_u16()
+            deserialize_u32()
+            deserialize_u64()
+            deserialize_u128()
+            deserialize_f32()
+            deserialize_f64()
+            deserialize_char()
+            deserialize_str()
+            deserialize_string()
+            deserialize_bytes()
+            deserialize_byte_buf()
+            deserialize_option()
+            deserialize_unit()
+            deserialize_unit_struct(name: &'static str)
+            deserialize_newtype_struct(name: &'static str)
+            deserialize_seq()
+            deserialize_tuple(len: usize)
+            deserialize_tuple_struct(name: &'static str, len: usize)
+            deserialize_map()
+            deserialize_struct(name: &'static str, fields: &'static [&'static str])
+            deserialize_enum(name: &'static str, variants: &'static [&'static str])
+            deserialize_identifier()
+            deserialize_ignored_any()
+        }
+    };
+}
+
+// Forwards `visit_*` methods taking a plain value to `self.0` unchanged.
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty))*) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                self.0.$method(v)
+            }
+        )*
+    };
+}
+
+/// A deserializer whose maps, sequences and enums extend the tracked path.
+struct Tracked<D>(D);
+
+impl<D> Tracked<D> {
+    fn split<V>(self, visitor: V) -> (D, TrackedVisitor<V>) {
+        (self.0, TrackedVisitor(visitor))
+    }
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Tracked<D> {
+    type Error = D::Error;
+
+    deserialize_methods!();
+}
+
+struct TrackedSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for TrackedSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize(Tracked(deserializer))
+    }
+}
+
+struct TrackedVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for TrackedVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool)
+        visit_i8(i8)
+        visit_i16(i16)
+        visit_i32(i32)
+        visit_i64(i64)
+        visit_i128(i128)
+        visit_u8(u8)
+        visit_u16(u16)
+        visit_u32(u32)
+        visit_u64(u64)
+        visit_u128(u128)
+        visit_f32(f32)
+        visit_f64(f64)
+        visit_char(char)
+        visit_str(&str)
+        visit_borrowed_str(&'de str)
+        visit_string(String)
+        visit_bytes(&[u8])
+        visit_borrowed_bytes(&'de [u8])
+        visit_byte_buf(Vec<u8>)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_some(Tracked(deserializer))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_newtype_struct(Tracked(deserializer))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        self.0.visit_seq(TrackedSeq { seq, index: 0 })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        self.0.visit_map(TrackedMap { map, key: None })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.0.visit_enum(TrackedEnum(data))
+    }
+}
+
+struct TrackedSeq<A> {
+    seq: A,
+    index: usize,
+}
+
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for TrackedSeq<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let index = self.index;
+        self.index += 1;
+        descend(Segment::Index(index), || {
+            self.seq.next_element_seed(TrackedSeed(seed))
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+struct TrackedMap<A> {
+    map: A,
+    key: Option<String>,
+}
+
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for TrackedMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.key = None;
+        self.map.next_key_seed(CaptureKey {
+            seed,
+            key: &mut self.key,
+        })
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let segment = self.key.take().map_or(Segment::Unknown, Segment::Key);
+        descend(segment, || self.map.next_value_seed(TrackedSeed(seed)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct TrackedEnum<A>(A);
+
+impl<'de, A: de::EnumAccess<'de>> de::EnumAccess<'de> for TrackedEnum<A> {
+    type Error = A::Error;
+    type Variant = TrackedVariant<A::Variant>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let mut key = None;
+        let (value, variant) = self.0.variant_seed(CaptureKey {
+            seed,
+            key: &mut key,
+        })?;
+        let segment = key.map_or(Segment::Unknown, Segment::Key);
+        Ok((value, TrackedVariant { variant, segment }))
+    }
+}
+
+struct TrackedVariant<A> {
+    variant: A,
+    segment: Segment,
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::VariantAccess<'de> for TrackedVariant<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.variant.unit_variant()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let variant = self.variant;
+        descend(self.segment, || {
+            variant.newtype_variant_seed(TrackedSeed(seed))
+        })
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let variant = self.variant;
+        descend(self.segment, || {
+            variant.tuple_variant(len, TrackedVisitor(visitor))
+        })
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let variant = self.variant;
+        descend(self.segment, || {
+            variant.struct_variant(fields, TrackedVisitor(visitor))
+        })
+    }
+}
+
+/// A map key or variant name, passed on unchanged while keeping a printable copy for the path.
+struct CaptureKey<'a, S> {
+    seed: S,
+    key: &'a mut Option<String>,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for CaptureKey<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.seed
+            .deserialize(CaptureKeyDeserializer(deserializer, self.key))
+    }
+}
+
+struct CaptureKeyDeserializer<'a, D>(D, &'a mut Option<String>);
+
+impl<'a, D> CaptureKeyDeserializer<'a, D> {
+    fn split<V>(self, visitor: V) -> (D, CaptureKeyVisitor<'a, V>) {
+        (self.0, CaptureKeyVisitor(visitor, self.1))
+    }
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for CaptureKeyDeserializer<'_, D> {
+    type Error = D::Error;
+
+    deserialize_methods!();
+}
+
+struct CaptureKeyVisitor<'a, V>(V, &'a mut Option<String>);
+
+// Keeps a copy of keys that read well in a path before passing them on.
+macro_rules! capture_visit {
+    ($($method:ident($ty:ty))*) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                *self.1 = Some(v.to_string());
+                self.0.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for CaptureKeyVisitor<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(f)
+    }
+
+    capture_visit! {
+        visit_bool(bool)
+        visit_i8(i8)
+        visit_i16(i16)
+        visit_i32(i32)
+        visit_i64(i64)
+        visit_i128(i128)
+        visit_u8(u8)
+        visit_u16(u16)
+        visit_u32(u32)
+        visit_u64(u64)
+        visit_u128(u128)
+        visit_char(char)
+        visit_str(&str)
+        visit_borrowed_str(&'de str)
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        *self.1 = Some(v.clone());
+        self.0.visit_string(v)
+    }
+
+    forward_visit! {
+        visit_f32(f32)
+        visit_f64(f64)
+        visit_bytes(&[u8])
+        visit_borrowed_bytes(&'de [u8])
+        visit_byte_buf(Vec<u8>)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        self.0.visit_seq(seq)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        self.0.visit_map(map)
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.0.visit_enum(data)
+    }
+}